@@ -0,0 +1,218 @@
+//parsing and validation of the addresses given to SOUNDZ, VIDZ and captions=
+
+/// what kind of address was written
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressKind {
+    Url,              // https://example.com/a.mp3
+    ProtocolRelative, // //example.com/a.mp3
+    Relative,         // media/a.mp3
+}
+
+/// an address split into its url parts, the original text is kept as written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub text: String,
+    pub kind: AddressKind,
+    pub scheme: Option<String>,
+    pub host: Option<String>,
+    pub path: String,
+    pub query: Option<String>,
+    pub fragment: Option<String>,
+}
+
+//schemes a browser will happily run as script, never allowed
+const REFUSED_SCHEMES: [&str; 3] = ["javascript", "vbscript", "data"];
+
+impl Address {
+    /// parses and validates an address, problems the browser can live with become warnings
+    pub fn parse(text: &str, warnings: &mut Vec<String>) -> Result<Address, String> {
+        if text.is_empty() {
+            return Err("Address Error: Expected an address (URL or relative path) before #MKAY, found nothing.".to_string());
+        }
+        if let Some(c) = text.chars().find(|c| c.is_whitespace() || c.is_control()) {
+            return Err(format!("Address Error: Address '{}' contains {:?}; percent-encode it (a space is %20).", text, c));
+        }
+        if let Some(c) = text.chars().find(|c| matches!(c, '"' | '<' | '>')) {
+            return Err(format!("Address Error: Address '{}' contains '{}', which must be percent-encoded.", text, c));
+        }
+        if text.contains('\\') {
+            warnings.push(format!("Warning: Address '{}' contains a backslash; use '/' to separate path segments.", text));
+        }
+        check_percent_encoding(text, warnings);
+
+        let (rest, fragment) = match text.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment.to_string())),
+            None => (text, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query.to_string())),
+            None => (rest, None),
+        };
+
+        let mut address = Address {
+            text: text.to_string(),
+            kind: AddressKind::Relative,
+            scheme: None,
+            host: None,
+            path: rest.to_string(),
+            query,
+            fragment,
+        };
+
+        if let Some(authority_and_path) = rest.strip_prefix("//") {
+            address.kind = AddressKind::ProtocolRelative;
+            address.set_authority(authority_and_path);
+            if address.host.is_none() {
+                return Err(format!("Address Error: Address '{}' is missing a host name.", text));
+            }
+            return Ok(address);
+        }
+
+        let scheme = match scheme_of(rest) {
+            Some(scheme) => scheme,
+            None => return Ok(address),
+        };
+        let after_scheme = &rest[scheme.len() + 1..];
+        let scheme = scheme.to_lowercase();
+
+        if REFUSED_SCHEMES.contains(&scheme.as_str()) {
+            return Err(format!("Address Error: '{}:' addresses are not allowed ('{}').", scheme, text));
+        }
+        if scheme.len() == 1 {
+            warnings.push(format!("Warning: Address '{}' looks like a drive path on this computer; use a path relative to the .lol file.", text));
+            return Ok(address);
+        }
+
+        address.kind = AddressKind::Url;
+        match after_scheme.strip_prefix("//") {
+            Some(authority_and_path) => address.set_authority(authority_and_path),
+            None => address.path = after_scheme.to_string(),
+        }
+
+        match scheme.as_str() {
+            "http" | "https" if address.host.is_none() => {
+                return Err(format!("Address Error: Address '{}' is missing a host name.", text));
+            }
+            "http" | "https" => {}
+            _ => warnings.push(format!("Warning: Address '{}' uses the unsupported scheme '{}:'; browsers may not play it.", text, scheme)),
+        }
+        address.scheme = Some(scheme);
+        Ok(address)
+    }
+
+    /// true for addresses that point at a file relative to the source file
+    pub fn is_local(&self) -> bool {
+        self.kind == AddressKind::Relative && self.scheme.is_none()
+    }
+
+    /// the path with %XX escapes turned back into characters, for finding the file on disk
+    pub fn decoded_path(&self) -> String {
+        let bytes = self.path.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+            match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(b) if bytes[i] == b'%' => {
+                    decoded.push(b);
+                    i += 3;
+                }
+                _ => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    //fills host and path from "host:port/path"
+    fn set_authority(&mut self, authority_and_path: &str) {
+        let (authority, path) = match authority_and_path.find('/') {
+            Some(i) => authority_and_path.split_at(i),
+            None => (authority_and_path, ""),
+        };
+        let host = authority.rsplit('@').next().unwrap_or(authority);
+        let host = host.split(':').next().unwrap_or(host).to_lowercase();
+        self.host = if host.is_empty() { None } else { Some(host) };
+        self.path = path.to_string();
+    }
+}
+
+//scheme ::= letter { letter | digit | "+" | "-" | "." } ":"
+fn scheme_of(text: &str) -> Option<&str> {
+    let (scheme, _) = text.split_once(':')?;
+    let mut chars = scheme.chars();
+    let first = chars.next()?;
+    if first.is_ascii_alphabetic() && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+fn check_percent_encoding(text: &str, warnings: &mut Vec<String>) {
+    let bytes = text.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'%' {
+            let valid = bytes.len() > i + 2 && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit();
+            if !valid {
+                warnings.push(format!("Warning: Address '{}' has a malformed percent-encoding at position {}; write %25 for a literal '%'.", text, i + 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let mut warnings = Vec::new();
+        let address = Address::parse("http://www.televisiontunes.com/themesongs/The%20Simpsons.mp3?x=1#t=5", &mut warnings).unwrap();
+
+        assert_eq!(address.kind, AddressKind::Url);
+        assert_eq!(address.scheme, Some("http".to_string()));
+        assert_eq!(address.host, Some("www.televisiontunes.com".to_string()));
+        assert_eq!(address.path, "/themesongs/The%20Simpsons.mp3");
+        assert_eq!(address.query, Some("x=1".to_string()));
+        assert_eq!(address.fragment, Some("t=5".to_string()));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        let mut warnings = Vec::new();
+        let address = Address::parse("media/theme.ogg", &mut warnings).unwrap();
+        assert!(address.is_local());
+        assert_eq!(address.path, "media/theme.ogg");
+
+        let address = Address::parse("media/The%20Simpsons.mp3#t=5", &mut warnings).unwrap();
+        assert_eq!(address.decoded_path(), "media/The Simpsons.mp3");
+
+        let address = Address::parse("//cdn.example.com/theme.ogg", &mut warnings).unwrap();
+        assert_eq!(address.kind, AddressKind::ProtocolRelative);
+        assert!(!address.is_local());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_address_errors() {
+        let mut warnings = Vec::new();
+        assert!(Address::parse("", &mut warnings).is_err());
+        assert!(Address::parse("JavaScript:alert(1)", &mut warnings).is_err());
+        assert!(Address::parse("https:///nohost.mp4", &mut warnings).is_err());
+        assert!(Address::parse("a\"b.mp3", &mut warnings).is_err());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_address_warnings() {
+        let mut warnings = Vec::new();
+        Address::parse("songs/100%.mp3", &mut warnings).unwrap();
+        Address::parse("ftp://example.com/a.mp3", &mut warnings).unwrap();
+        Address::parse("C:\\music\\a.mp3", &mut warnings).unwrap();
+        assert_eq!(warnings.len(), 4);
+    }
+}
@@ -0,0 +1,237 @@
+//local media files referenced by a document: existence checks and copying into --out-dir
+use crate::address::Address;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// a local file the generated page points at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asset {
+    pub source: PathBuf, // where the file is on disk
+    pub output: String,  // path used in the html, relative to the output file
+}
+
+impl Asset {
+    /// output with %XX escapes turned back into characters, the path of the copy on disk
+    pub fn output_path(&self) -> String {
+        decode(&self.output)
+    }
+}
+
+/// collects local media while parsing so it can be copied next to the html afterwards
+#[derive(Debug, Default)]
+pub struct Assets {
+    source_dir: PathBuf,
+    copied: bool, // the files go into an output folder or bundle, so addresses may be rewritten
+    hash_names: bool,
+    files: Vec<Asset>,
+}
+
+impl Assets {
+    /// source_dir is the folder of the .lol file, relative addresses are looked up from there.
+    /// copied says the files will be copied out, only then are their addresses rewritten
+    pub fn new(source_dir: &Path, copied: bool, hash_names: bool) -> Self {
+        Assets {
+            source_dir: source_dir.to_path_buf(),
+            copied,
+            hash_names,
+            files: Vec::new(),
+        }
+    }
+
+    /// checks that a local address exists and returns the address the html should use,
+    /// addresses that are not local are returned unchanged
+    pub fn resolve(&mut self, address: &Address, warnings: &mut Vec<String>) -> String {
+        if !address.is_local() {
+            return address.text.clone();
+        }
+        let relative = address.decoded_path();
+        let source = self.source_dir.join(&relative);
+        if !source.is_file() {
+            warnings.push(format!("Warning: Media file '{}' not found (looked for '{}').", address.text, source.display()));
+            return address.text.clone();
+        }
+
+        //left where it is, the page points at the file itself
+        if !self.copied {
+            return address.text.clone();
+        }
+        if let Some(asset) = self.files.iter().find(|a| a.source == source) {
+            return with_suffix(&asset.output, address);
+        }
+
+        //files outside the source folder cannot keep their path inside the output folder
+        let mut output = if stays_inside(Path::new(&relative)) {
+            address.path.trim_start_matches("./").to_string()
+        } else {
+            let file_name = source.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            format!("assets/{}", encode_path(&file_name))
+        };
+        if self.hash_names {
+            match fs::read(&source) {
+                Ok(bytes) => output = hashed_name(&output, fnv1a(&bytes)),
+                Err(e) => warnings.push(format!("Warning: Could not read media file '{}': {}", source.display(), e)),
+            }
+        }
+        //../a/x.mp3 and ../b/x.mp3 would both be assets/x.mp3
+        let base = output.clone();
+        let mut n = 1;
+        while self.files.iter().any(|a| a.output == output) {
+            n += 1;
+            output = tagged_name(&base, &format!("-{}", n));
+        }
+
+        self.files.push(Asset { source, output: output.clone() });
+        with_suffix(&output, address)
+    }
+
+    pub fn files(&self) -> &[Asset] {
+        &self.files
+    }
+
+    /// copies every collected file under out_dir, creating folders as needed
+    pub fn copy_to(&self, out_dir: &Path) -> Result<(), String> {
+        for asset in &self.files {
+            let target = out_dir.join(asset.output_path());
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Code Generation Error: Could not create folder '{}': {}", parent.display(), e))?;
+            }
+            fs::copy(&asset.source, &target).map_err(|e| {
+                format!("Code Generation Error: Could not copy '{}' to '{}': {}", asset.source.display(), target.display(), e)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+//puts the original ?query and #fragment back on a rewritten path
+fn with_suffix(path: &str, address: &Address) -> String {
+    let mut text = path.to_string();
+    if let Some(query) = &address.query {
+        text.push('?');
+        text.push_str(query);
+    }
+    if let Some(fragment) = &address.fragment {
+        text.push('#');
+        text.push_str(fragment);
+    }
+    text
+}
+
+fn stays_inside(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+//media/theme.ogg -> media/theme.1a2b3c4d.ogg, so a changed file gets a new address
+fn hashed_name(path: &str, hash: u64) -> String {
+    let short = format!("{:016x}", hash);
+    tagged_name(path, &format!(".{}", &short[..8]))
+}
+
+//the tag goes between the file's stem and its extension
+fn tagged_name(path: &str, tag: &str) -> String {
+    let (dir, file) = match path.rsplit_once('/') {
+        Some((dir, file)) => (format!("{}/", dir), file),
+        None => (String::new(), path),
+    };
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}{}{}.{}", dir, stem, tag, ext),
+        _ => format!("{}{}{}", dir, file, tag),
+    }
+}
+
+//64 bit FNV-1a, good enough to tell file versions apart
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn encode_path(name: &str) -> String {
+    let mut encoded = String::new();
+    for b in name.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+fn decode(path: &str) -> String {
+    let mut warnings = Vec::new();
+    match Address::parse(path, &mut warnings) {
+        Ok(address) => address.decoded_path(),
+        Err(_) => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_name() {
+        assert_eq!(hashed_name("media/theme.ogg", 0x1a2b3c4d55667788), "media/theme.1a2b3c4d.ogg");
+        assert_eq!(hashed_name("README", 0x1a2b3c4d55667788), "README.1a2b3c4d");
+    }
+
+    #[test]
+    fn test_resolve_and_copy() {
+        let root = std::env::temp_dir().join(format!("lol_assets_{}", std::process::id()));
+        let source_dir = root.join("src");
+        fs::create_dir_all(source_dir.join("media")).unwrap();
+        fs::write(source_dir.join("media/theme song.ogg"), b"not really audio").unwrap();
+
+        let mut warnings = Vec::new();
+        let mut assets = Assets::new(&source_dir, true, true);
+        let found = Address::parse("media/theme%20song.ogg#t=5", &mut warnings).unwrap();
+        let missing = Address::parse("media/missing.mp3", &mut warnings).unwrap();
+        let remote = Address::parse("https://example.com/a.mp3", &mut warnings).unwrap();
+
+        let resolved = assets.resolve(&found, &mut warnings);
+        assert!(resolved.starts_with("media/theme%20song."));
+        assert!(resolved.ends_with(".ogg#t=5"));
+        assert_eq!(assets.resolve(&missing, &mut warnings), "media/missing.mp3");
+        assert_eq!(assets.resolve(&remote, &mut warnings), "https://example.com/a.mp3");
+        assert_eq!(warnings.len(), 1);
+
+        let out_dir = root.join("out");
+        assets.copy_to(&out_dir).unwrap();
+        let copied = out_dir.join(decode(&assets.files[0].output));
+        assert_eq!(fs::read(copied).unwrap(), b"not really audio");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    //files outside the source folder only move when they are copied, and never onto each other
+    #[test]
+    fn test_files_outside_the_source_folder() {
+        let root = std::env::temp_dir().join(format!("lol_assets_outside_{}", std::process::id()));
+        let source_dir = root.join("src");
+        fs::create_dir_all(&source_dir).unwrap();
+        for folder in ["a", "b"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+            fs::write(root.join(folder).join("x.mp3"), folder).unwrap();
+        }
+        let mut warnings = Vec::new();
+        let a = Address::parse("../a/x.mp3", &mut warnings).unwrap();
+        let b = Address::parse("../b/x.mp3", &mut warnings).unwrap();
+
+        let mut in_place = Assets::new(&source_dir, false, false);
+        assert_eq!(in_place.resolve(&a, &mut warnings), "../a/x.mp3");
+        assert!(in_place.files().is_empty());
+
+        let mut copied = Assets::new(&source_dir, true, false);
+        assert_eq!(copied.resolve(&a, &mut warnings), "assets/x.mp3");
+        assert_eq!(copied.resolve(&b, &mut warnings), "assets/x-2.mp3");
+        assert_eq!(copied.resolve(&a, &mut warnings), "assets/x.mp3");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+use crate::address::Address;
+use crate::media::MediaOptions;
+
+/// parsed document, what the parser hands to the html renderer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub prologue: Vec<Node>,      // definitions and comments between #HAI and the head
+    pub head: Option<Vec<Node>>,  // #MAEK HEAD ... #OIC
+    pub body: Vec<Node>,
+    pub trailing: Vec<String>,    // text of the comments after #KTHXBYE, ignored
+}
+
+/// an address plus the options written after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Media {
+    pub address: Address,
+    pub options: MediaOptions,
+    pub variables: Vec<Node>, // the #LEMME SEE uses read into the address and options
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Title(Vec<Node>),
+    Paragraph(Vec<Node>),
+    List(Vec<Node>),
+    Item(Vec<Node>),
+    Bold(Vec<Node>),
+    Italics(Vec<Node>),
+    Text(String),
+    Newline,
+    Audio(Media),
+    Video(Media),
+    Define { name: String, value: Vec<Node> },
+    Variable { name: String, value: Vec<Node> }, // value is the resolved definition
+    Comment(String),
+}
+
+/// the text of a fragment without its markup, for places that only take text
+/// (the title, addresses, media options)
+pub fn plain_text(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(s) => text.push_str(s),
+            Node::Bold(children) | Node::Italics(children) | Node::Title(children) => text.push_str(&plain_text(children)),
+            Node::Variable { value, .. } => text.push_str(&plain_text(value)),
+            Node::Newline => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
@@ -0,0 +1,578 @@
+//the document tree as versioned json, for tools that index or search documents without parsing
+//lolcode themselves. spans come from the syntax tree, which the document tree does not keep,
+//and every variable use points at the definition it was resolved to.
+//version 2: {"format": "lolcode-ast", "version": 2, "prologue": [..], "head": [..] | null,
+//"body": [..], "trailing": [".."]}, a node is an object with "type" and "span".
+//version 1 had "trailing": ".." | null, holding at most one comment
+use crate::address::Address;
+use crate::ast::{Document, Media, Node};
+use crate::cst::{Cst, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::json::{self, Value};
+use crate::lexer::{self, Span, Token};
+use crate::media::{self, MediaOptions};
+use crate::scope::Scopes;
+use crate::suggest;
+use crate::whitespace;
+
+pub const FORMAT: &str = "lolcode-ast";
+/// raised whenever a field changes meaning or a required one is added
+pub const VERSION: u64 = 2;
+
+/// the document as json, cst is the syntax tree it was parsed from
+pub fn export(document: &Document, cst: &Cst) -> String {
+    let mut exporter = Exporter { scopes: Scopes::new() };
+    let mut cursor = Cursor::new(&cst.root.children);
+    let prologue = exporter.nodes(&document.prologue, &mut cursor);
+    let head = match &document.head {
+        Some(head) => {
+            let syntax = cursor.node(SyntaxKind::Head);
+            Value::Array(exporter.nodes(head, &mut Cursor::inside(syntax)))
+        }
+        None => Value::Null,
+    };
+    let body = exporter.nodes(&document.body, &mut cursor);
+
+    let mut output = object(vec![
+        ("format", Value::String(FORMAT.to_string())),
+        ("version", Value::Number(VERSION as f64)),
+        ("prologue", Value::Array(prologue)),
+        ("head", head),
+        ("body", Value::Array(body)),
+        ("trailing", Value::Array(document.trailing.iter().map(|comment| string(comment)).collect())),
+    ])
+    .to_pretty_string();
+    output.push('\n');
+    output
+}
+
+struct Exporter<'a> {
+    scopes: Scopes<Option<&'a SyntaxNode>>, // where each visible definition was written
+}
+
+impl<'a> Exporter<'a> {
+    fn nodes(&mut self, nodes: &[Node], cursor: &mut Cursor<'a>) -> Vec<Value> {
+        nodes.iter().map(|node| self.node(node, cursor)).collect()
+    }
+
+    fn node(&mut self, node: &Node, cursor: &mut Cursor<'a>) -> Value {
+        match node {
+            Node::Title(children) | Node::Paragraph(children) | Node::List(children) | Node::Item(children)
+            | Node::Bold(children) | Node::Italics(children) => {
+                let (name, kind) = match node {
+                    Node::Title(_) => ("title", SyntaxKind::Title),
+                    Node::Paragraph(_) => ("paragraph", SyntaxKind::Paragraph),
+                    Node::List(_) => ("list", SyntaxKind::List),
+                    Node::Item(_) => ("item", SyntaxKind::Item),
+                    Node::Bold(_) => ("bold", SyntaxKind::Bold),
+                    _ => ("italics", SyntaxKind::Italics),
+                };
+                let syntax = cursor.node(kind);
+                //paragraphs, lists and list items each have their own frame, like in the parser
+                let block = matches!(node, Node::Paragraph(_) | Node::List(_) | Node::Item(_));
+                if block {
+                    self.scopes.push();
+                }
+                let children = self.nodes(children, &mut Cursor::inside(syntax));
+                if block {
+                    self.scopes.pop();
+                }
+                object(vec![("type", string(name)), ("span", node_span(syntax)), ("children", Value::Array(children))])
+            }
+            Node::Text(text) => {
+                let wanted = collapse(text);
+                let leaf = cursor.leaf(|token| match token {
+                    Token::Text(t) | Token::VarVal(t) => collapse(t) == wanted,
+                    _ => false,
+                });
+                object(vec![("type", string("text")), ("span", leaf_span(leaf)), ("text", string(text))])
+            }
+            Node::Newline => {
+                let syntax = cursor.node(SyntaxKind::Newline);
+                object(vec![("type", string("newline")), ("span", node_span(syntax))])
+            }
+            Node::Audio(media) | Node::Video(media) => {
+                let (name, kind) = match node {
+                    Node::Audio(_) => ("audio", SyntaxKind::Audio),
+                    _ => ("video", SyntaxKind::Video),
+                };
+                let syntax = cursor.node(kind);
+                let variables = self.nodes(&media.variables, &mut Cursor::inside(syntax));
+                object(vec![
+                    ("type", string(name)),
+                    ("span", node_span(syntax)),
+                    ("address", string(&media.address.text)),
+                    ("options", options(&media.options)),
+                    ("variables", Value::Array(variables)),
+                ])
+            }
+            Node::Define { name, value } => {
+                let syntax = cursor.node(SyntaxKind::Define);
+                let value = self.nodes(value, &mut Cursor::inside(syntax));
+                //defined after its value is read, the value sees the outer definition
+                self.scopes.define(name.clone(), syntax);
+                object(vec![("type", string("define")), ("span", node_span(syntax)), ("name", string(name)), ("value", Value::Array(value))])
+            }
+            //the value is the resolved definition, its spans point at where the definition was written
+            Node::Variable { name, value } => {
+                let syntax = cursor.node(SyntaxKind::Variable);
+                let definition = self.scopes.lookup(name).copied().flatten();
+                let binding = node_span(definition);
+                let value = self.nodes(value, &mut Cursor::inside(definition));
+                object(vec![
+                    ("type", string("variable")),
+                    ("span", node_span(syntax)),
+                    ("name", string(name)),
+                    ("binding", binding),
+                    ("value", Value::Array(value)),
+                ])
+            }
+            Node::Comment(text) => {
+                let leaf = cursor.leaf(|token| *token == Token::Comment(text.clone()));
+                object(vec![("type", string("comment")), ("span", leaf_span(leaf)), ("text", string(text))])
+            }
+        }
+    }
+}
+
+//walks the children of a syntax node alongside the document nodes made from them. the parser
+//drops keywords and blank text, so each lookup skips ahead to the next element that fits, and
+//when nothing fits the node gets no span and the position stays where it was
+struct Cursor<'a> {
+    children: &'a [SyntaxElement],
+    next: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(children: &'a [SyntaxElement]) -> Self {
+        Cursor { children, next: 0 }
+    }
+
+    fn inside(node: Option<&'a SyntaxNode>) -> Self {
+        Cursor::new(node.map_or(&[], |node| node.children.as_slice()))
+    }
+
+    fn node(&mut self, kind: SyntaxKind) -> Option<&'a SyntaxNode> {
+        let children = self.children;
+        let (i, node) = children.iter().enumerate().skip(self.next).find_map(|(i, child)| match child {
+            SyntaxElement::Node(node) if node.kind == kind => Some((i, node)),
+            _ => None,
+        })?;
+        self.next = i + 1;
+        Some(node)
+    }
+
+    fn leaf(&mut self, fits: impl Fn(&Token) -> bool) -> Option<&'a SyntaxToken> {
+        let children = self.children;
+        let (i, leaf) = children.iter().enumerate().skip(self.next).find_map(|(i, child)| match child {
+            SyntaxElement::Token(leaf) if leaf.token.as_ref().is_some_and(&fits) => Some((i, leaf)),
+            _ => None,
+        })?;
+        self.next = i + 1;
+        Some(leaf)
+    }
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+fn string(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+fn span(span: Span) -> Value {
+    object(vec![
+        ("start", Value::Number(span.start as f64)),
+        ("end", Value::Number(span.end as f64)),
+        ("line", Value::Number(span.line as f64)),
+    ])
+}
+
+fn node_span(node: Option<&SyntaxNode>) -> Value {
+    node.and_then(SyntaxNode::span).map_or(Value::Null, span)
+}
+
+fn leaf_span(leaf: Option<&SyntaxToken>) -> Value {
+    leaf.map_or(Value::Null, |leaf| span(leaf.span))
+}
+
+fn options(options: &MediaOptions) -> Value {
+    let number = |n: Option<u32>| n.map_or(Value::Null, |n| Value::Number(n as f64));
+    object(vec![
+        ("autoplay", Value::Bool(options.autoplay)),
+        ("loop", Value::Bool(options.looped)),
+        ("muted", Value::Bool(options.muted)),
+        ("width", number(options.width)),
+        ("height", number(options.height)),
+        ("start", number(options.start)),
+        ("captions", options.captions.clone().map_or(Value::Null, Value::String)),
+    ])
+}
+
+/// reads a document back from its json, spans and bindings are ignored. the nodes are held to the
+/// parser's rules, a json file is not trusted more than a .lol file
+pub fn import(text: &str) -> Result<Document, String> {
+    let value = json::parse(text)?;
+    if value.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err(format!("Import Error: Not an ast-json document, \"format\" must be \"{}\".", FORMAT));
+    }
+    match value.get("version").and_then(Value::as_u64) {
+        Some(version) if (1..=VERSION).contains(&version) => {}
+        Some(version) => return Err(format!("Import Error: ast-json version {} is not supported, this compiler reads version {}.", version, VERSION)),
+        None => return Err("Import Error: \"version\" must be a whole number.".to_string()),
+    }
+
+    let mut importer = Importer { scopes: Scopes::new() };
+    let prologue = importer.nodes(value.get("prologue").unwrap_or(&Value::Array(Vec::new())), "prologue", Place::Prologue)?;
+    let head = match value.get("head") {
+        None | Some(Value::Null) => None,
+        Some(head) => Some(importer.nodes(head, "head", Place::Head)?),
+    };
+    let trailing_error = || "Import Error: \"trailing\" must be an array of strings.".to_string();
+    let trailing = match value.get("trailing") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(comment)) => vec![comment.clone()], // version 1
+        Some(trailing) => trailing.as_array().ok_or_else(trailing_error)?.iter()
+            .map(|comment| comment.as_str().map(str::to_string).ok_or_else(trailing_error))
+            .collect::<Result<_, _>>()?,
+    };
+    let body = importer.nodes(value.get("body").unwrap_or(&Value::Null), "body", Place::Body)?;
+    let mut document = Document { prologue, head, body, trailing };
+    whitespace::normalize(&mut document);
+    Ok(document)
+}
+
+/// where a list of nodes sits, each place takes the nodes the parser reads there
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Place {
+    Prologue,
+    Head,
+    Body,
+    Paragraph,
+    List,
+    Item,
+    Title,
+    Styled, // the text of bold and italics
+    Value,  // the value of a define
+    Media,  // the variables read into a media address
+}
+
+impl Place {
+    fn name(self) -> &'static str {
+        match self {
+            Place::Prologue => "the prologue",
+            Place::Head => "the head",
+            Place::Body => "the body",
+            Place::Paragraph => "a paragraph",
+            Place::List => "a list",
+            Place::Item => "a list item",
+            Place::Title => "a title",
+            Place::Styled => "bold or italics",
+            Place::Value => "a variable value",
+            Place::Media => "a media address",
+        }
+    }
+
+    //comments may go anywhere
+    fn allows(self, kind: &str) -> bool {
+        kind == "comment"
+            || match self {
+                Place::Prologue => kind == "define",
+                Place::Head => kind == "title",
+                Place::Body => matches!(kind, "define" | "paragraph" | "list" | "variable" | "bold" | "italics" | "audio" | "video" | "newline" | "text"),
+                Place::Paragraph => matches!(kind, "define" | "list" | "variable" | "bold" | "italics" | "audio" | "video" | "newline" | "text"),
+                Place::List => matches!(kind, "define" | "item"),
+                Place::Item => matches!(kind, "define" | "variable" | "bold" | "italics" | "text"),
+                Place::Title | Place::Styled => matches!(kind, "variable" | "text"),
+                Place::Value => matches!(kind, "variable" | "bold" | "italics" | "newline" | "text"),
+                Place::Media => kind == "variable",
+            }
+    }
+}
+
+struct Importer {
+    scopes: Scopes<Vec<Node>>, // the value of each visible definition
+}
+
+impl Importer {
+    fn nodes(&mut self, value: &Value, field: &str, place: Place) -> Result<Vec<Node>, String> {
+        let items = value.as_array().ok_or_else(|| format!("Import Error: \"{}\" must be an array of nodes.", field))?;
+        let mut nodes = Vec::new();
+        let mut content = false;
+        for item in items {
+            let node = self.node(item)?;
+            let kind = node_type(&node);
+            if !place.allows(kind) {
+                return Err(format!("Import Error: A node of type '{}' is not allowed in {}.", kind, place.name()));
+            }
+            //blocks take their definitions first, the body takes them anywhere
+            if kind == "define" && content && place != Place::Body {
+                return Err(format!("Import Error: A node of type 'define' is only allowed at the start of {}.", place.name()));
+            }
+            content |= !matches!(kind, "define" | "comment");
+            nodes.push(node);
+        }
+
+        let count = |kind: &str| nodes.iter().filter(|node| node_type(node) == kind).count();
+        match place {
+            Place::Head if count("title") != 1 => Err("Import Error: The head must hold exactly one title node.".to_string()),
+            Place::List if count("item") == 0 => Err("Import Error: A list needs at least one item node.".to_string()),
+            Place::Title | Place::Value if !content => Err(format!("Import Error: {} needs at least one text or variable node.", capitalize(place.name()))),
+            _ => Ok(nodes),
+        }
+    }
+
+    fn node(&mut self, value: &Value) -> Result<Node, String> {
+        let kind = value.get("type").and_then(Value::as_str).ok_or("Import Error: Every node needs a \"type\".")?;
+        let field = |name: &str| -> Result<String, String> {
+            value.get(name).and_then(Value::as_str).map(str::to_string)
+                .ok_or_else(|| format!("Import Error: A {} node needs a \"{}\" string.", kind, name))
+        };
+        //the same names the lexer accepts after #I HAZ and #LEMME SEE
+        let name = || -> Result<String, String> {
+            let name = field("name")?;
+            if !lexer::is_identifier(&name) {
+                return Err(format!("Import Error: '{}' is not a valid variable name.", name));
+            }
+            Ok(name)
+        };
+
+        let node = match kind {
+            "title" => Node::Title(self.children(value, Place::Title)?),
+            "paragraph" => Node::Paragraph(self.children(value, Place::Paragraph)?),
+            "list" => Node::List(self.children(value, Place::List)?),
+            "item" => Node::Item(self.children(value, Place::Item)?),
+            "bold" => Node::Bold(self.children(value, Place::Styled)?),
+            "italics" => Node::Italics(self.children(value, Place::Styled)?),
+            "text" => Node::Text(field("text")?),
+            "newline" => Node::Newline,
+            "audio" => {
+                let variables = self.nodes(value.get("variables").unwrap_or(&Value::Array(Vec::new())), "variables", Place::Media)?;
+                let media = import_media(&field("address")?, value.get("options"), variables)?;
+                media::check_audio(&media.options)?;
+                Node::Audio(media)
+            }
+            "video" => {
+                let variables = self.nodes(value.get("variables").unwrap_or(&Value::Array(Vec::new())), "variables", Place::Media)?;
+                let media = import_media(&field("address")?, value.get("options"), variables)?;
+                //the warnings are for source files, only the errors stop an import
+                media::check_video(&media.address, &media.options, &mut Vec::new())?;
+                Node::Video(media)
+            }
+            "define" => {
+                let name = name()?;
+                //defined after its value is read, the value sees the outer definition
+                let value = self.nodes(value.get("value").unwrap_or(&Value::Null), "value", Place::Value)?;
+                self.scopes.define(name.clone(), value.clone());
+                Node::Define { name, value }
+            }
+            //the value is taken from the definition in scope, like the parser does
+            "variable" => {
+                let name = name()?;
+                match self.scopes.lookup(&name) {
+                    Some(value) => Node::Variable { value: value.clone(), name },
+                    None => return Err(format!("Semantic Error: Variable '{}' used but not defined.{}", name, suggest::hint(&name, self.scopes.names()))),
+                }
+            }
+            "comment" => Node::Comment(field("text")?),
+            _ => return Err(format!("Import Error: Unknown node type '{}'.", kind)),
+        };
+        Ok(node)
+    }
+
+    //paragraphs, lists and list items each have their own frame, like in the parser
+    fn children(&mut self, value: &Value, place: Place) -> Result<Vec<Node>, String> {
+        let block = matches!(place, Place::Paragraph | Place::List | Place::Item);
+        if block {
+            self.scopes.push();
+        }
+        let children = self.nodes(value.get("children").unwrap_or(&Value::Null), "children", place);
+        if block {
+            self.scopes.pop();
+        }
+        children
+    }
+}
+
+/// the "type" a node is written with
+fn node_type(node: &Node) -> &'static str {
+    match node {
+        Node::Title(_) => "title",
+        Node::Paragraph(_) => "paragraph",
+        Node::List(_) => "list",
+        Node::Item(_) => "item",
+        Node::Bold(_) => "bold",
+        Node::Italics(_) => "italics",
+        Node::Text(_) => "text",
+        Node::Newline => "newline",
+        Node::Audio(_) => "audio",
+        Node::Video(_) => "video",
+        Node::Define { .. } => "define",
+        Node::Variable { .. } => "variable",
+        Node::Comment(_) => "comment",
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+}
+
+//the address and the caption track are checked again, like the parser checks them
+fn import_media(address: &str, options: Option<&Value>, variables: Vec<Node>) -> Result<Media, String> {
+    let address = Address::parse(address, &mut Vec::new())?;
+    let options = options.unwrap_or(&Value::Null);
+    let flag = |name: &str| options.get(name).and_then(Value::as_bool).unwrap_or(false);
+    let number = |name: &str| -> Result<Option<u32>, String> {
+        match options.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()).map(Some)
+                .ok_or_else(|| format!("Import Error: Media option '{}' must be a whole number.", name)),
+        }
+    };
+    let captions = match options.get("captions") {
+        None | Some(Value::Null) => None,
+        Some(captions) => {
+            let captions = captions.as_str().ok_or("Import Error: Media option 'captions' must be a string or null.")?;
+            Some(Address::parse(captions, &mut Vec::new())?.text)
+        }
+    };
+    Ok(Media {
+        address,
+        options: MediaOptions {
+            autoplay: flag("autoplay"),
+            looped: flag("loop"),
+            muted: flag("muted"),
+            width: number("width")?,
+            height: number("height")?,
+            start: number("start")?,
+            captions,
+        },
+        variables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+    use crate::testing;
+    use std::path::Path;
+
+    #[test]
+    fn test_spans_and_bindings() {
+        let source = std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/Test8.lol")).unwrap();
+        let (document, cst) = (testing::parse(&source), Cst::parse(&source));
+        let value = json::parse(&export(&document, &cst)).unwrap();
+        assert_eq!(value.get("version").and_then(Value::as_u64), Some(VERSION));
+
+        //the first paragraph defines its own answer, the second one sees the global
+        let body = value.get("body").and_then(Value::as_array).unwrap();
+        let global = &body[0];
+        let first = body[1].get("children").and_then(Value::as_array).unwrap();
+        let second = body[2].get("children").and_then(Value::as_array).unwrap();
+        let local = &first[0];
+        let first_use = first.last().unwrap();
+        let second_use = second.last().unwrap();
+        assert_eq!(first_use.get("binding"), local.get("span"));
+        assert_eq!(second_use.get("binding"), global.get("span"));
+
+        let span = second_use.get("span").unwrap();
+        let (start, end) = (span.get("start").and_then(Value::as_u64).unwrap(), span.get("end").and_then(Value::as_u64).unwrap());
+        assert_eq!(&source[start as usize..end as usize], "#LEMME SEE answer #MKAY");
+        assert_eq!(span.get("line").and_then(Value::as_u64), Some(15));
+    }
+
+    #[test]
+    fn test_every_test_file_round_trips() {
+        for file in testing::test_files() {
+            let json = export(&file.document, &file.cst);
+            assert!(!json.contains("\"span\": null"), "{}", file.path.display());
+            let imported = import(&json).unwrap();
+            assert_eq!(imported, file.document, "{}", file.path.display());
+            assert_eq!(html::render(&imported, true), html::render(&file.document, true));
+        }
+    }
+
+    #[test]
+    fn test_import_errors() {
+        assert!(import("{\"format\": \"lolcode-ast\", \"version\": 99, \"body\": []}").unwrap_err().contains("version 99"));
+        assert!(import("{\"version\": 1, \"body\": []}").is_err());
+        let script = "{\"format\": \"lolcode-ast\", \"version\": 1, \"body\": [{\"type\": \"video\", \"address\": \"javascript:alert(1)\"}]}";
+        assert!(import(script).is_err());
+
+        let import_body = |body: &str| import(&format!("{{\"format\": \"lolcode-ast\", \"version\": 1, \"body\": [{}]}}", body));
+        let define = |name: &str| format!("{{\"type\": \"define\", \"name\": \"{}\", \"value\": [{{\"type\": \"text\", \"text\": \"x\"}}]}}", name);
+        assert!(import_body(&define("answer")).is_ok());
+        assert!(import_body(&define("my answer")).unwrap_err().contains("not a valid variable name"));
+        let unbound = format!("{{\"type\": \"paragraph\", \"children\": [{}]}}, {{\"type\": \"variable\", \"name\": \"answer\"}}", define("answer"));
+        assert!(import_body(&unbound).unwrap_err().contains("'answer' used but not defined"));
+        let captions = "{\"type\": \"video\", \"address\": \"clip.mp4\", \"options\": {\"captions\": \"javascript:alert(1)\"}}";
+        assert!(import_body(captions).is_err());
+        assert!(import_body("{\"type\": \"video\", \"address\": \"clip.mp4\", \"options\": {\"width\": 1e999}}").is_err());
+    }
+
+    //each node is only taken where the parser would have read it
+    #[test]
+    fn test_import_structure() {
+        let import_body = |body: &str| import(&format!(r#"{{"format": "lolcode-ast", "version": 1, "body": [{}]}}"#, body));
+        let text = r#"{"type": "text", "text": "x"}"#;
+        let define = r#"{"type": "define", "name": "a", "value": [{"type": "text", "text": "x"}]}"#;
+        let node = |kind: &str, children: &str| format!(r#"{{"type": "{}", "children": [{}]}}"#, kind, children);
+        let rejected = |body: &str, message: &str| {
+            let error = import_body(body).unwrap_err();
+            assert!(error.contains(message), "{}: {}", body, error);
+        };
+
+        rejected(&node("bold", &node("paragraph", text)), "A node of type 'paragraph' is not allowed in bold or italics.");
+        rejected(&node("paragraph", &node("title", text)), "A node of type 'title' is not allowed in a paragraph.");
+        rejected(&node("title", text), "A node of type 'title' is not allowed in the body.");
+        rejected(&node("item", text), "A node of type 'item' is not allowed in the body.");
+        rejected(&node("list", ""), "A list needs at least one item node.");
+        rejected(&node("list", text), "A node of type 'text' is not allowed in a list.");
+        rejected(&node("paragraph", &node("list", "")), "A list needs at least one item node.");
+        rejected(&node("paragraph", &format!("{}, {}", text, define)), "A node of type 'define' is only allowed at the start of a paragraph.");
+        rejected(r#"{"type": "define", "name": "a", "value": []}"#, "A variable value needs at least one text or variable node.");
+        rejected(r#"{"type": "audio", "address": "a.mp3", "options": {"width": 100}}"#, "width/height are not supported");
+        rejected(r#"{"type": "video", "address": "https://vimeo.com/76979871", "options": {"captions": "a.vtt"}}"#, "captions= cannot be attached");
+        assert!(import_body(&format!("{}, {}, {}", text, define, text)).is_ok());
+        assert!(import_body(&node("paragraph", &format!("{}, {}", define, text))).is_ok());
+
+        let head = |head: &str| import(&format!(r#"{{"format": "lolcode-ast", "version": 1, "head": [{}], "body": []}}"#, head));
+        assert!(head(&node("paragraph", text)).unwrap_err().contains("A node of type 'paragraph' is not allowed in the head."));
+        assert!(head("").unwrap_err().contains("exactly one title"));
+        assert!(head(&node("title", "")).unwrap_err().contains("A title needs at least one text or variable node."));
+
+        //whitespace is normalized as it is for a parsed document
+        let spaced = import_body(&node("paragraph", r#"{"type": "text", "text": "  fish \n  and chips "}"#)).unwrap();
+        assert_eq!(spaced.body, [Node::Paragraph(vec![Node::Text("fish and chips".to_string())])]);
+    }
+
+    #[test]
+    fn test_variables_in_media_addresses() {
+        let source = "#HAI #I HAZ base #IT IZ clips #MKAY #GIMMEH SOUNDZ #LEMME SEE base #MKAY/a.mp3 #MKAY #KTHXBYE";
+        let (document, cst) = (testing::parse(source), Cst::parse(source));
+        let json = export(&document, &cst);
+        let value = json::parse(&json).unwrap();
+        let definition = &value.get("prologue").and_then(Value::as_array).unwrap()[0];
+        let audio = &value.get("body").and_then(Value::as_array).unwrap()[0];
+        let variables = audio.get("variables").and_then(Value::as_array).unwrap();
+        assert_eq!(variables[0].get("name").and_then(Value::as_str), Some("base"));
+        assert_eq!(variables[0].get("binding"), definition.get("span"));
+        assert_eq!(import(&json).unwrap(), document);
+    }
+
+    #[test]
+    fn test_trailing_comments() {
+        let source = "#HAI #KTHXBYE\n#OBTW bye #TLDR\n#OBTW for now #TLDR\n";
+        let (document, cst) = (testing::parse(source), Cst::parse(source));
+        let json = export(&document, &cst);
+        let trailing = json::parse(&json).unwrap().get("trailing").cloned();
+        assert_eq!(trailing, Some(Value::Array(vec![string(" bye "), string(" for now ")])));
+        assert_eq!(import(&json).unwrap().trailing, [" bye ", " for now "]);
+    }
+}
@@ -0,0 +1,91 @@
+//the lolcode source the converters write (markdown import and html decompile). text has to come
+//back from the lexer as the same text, which depends on what was written before it
+use crate::lexer::{self, Lexer, Token};
+
+/// .lol source built up piece by piece, starting with #HAI
+pub struct SourceWriter {
+    output: String,
+    in_text: bool,          // text written now joins a text run or a keyword that reads text
+    variables: Vec<String>, // words that would be read as keywords, see push_text
+}
+
+impl SourceWriter {
+    pub fn new() -> Self {
+        SourceWriter { output: String::from("#HAI\n"), in_text: false, variables: Vec::new() }
+    }
+
+    /// source with keywords in it. each piece lexes on its own, so only the piece is lexed to
+    /// see where it leaves off
+    pub fn push_str(&mut self, code: &str) {
+        let (tokens, _) = Lexer::new(code).lex_recovering();
+        if let Some(last) = tokens.last() {
+            self.in_text = matches!(last.token, Token::Text(_) | Token::Bold | Token::Italics | Token::Title);
+        }
+        self.output.push_str(code);
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// text without #, written so it lexes back to the same text. a text run starting with
+    /// HEAD, PARAGRAF or LIST is read as that keyword and there is no escape, so the word is
+    /// written as a variable holding it, defined at the top by finish
+    pub fn push_text(&mut self, text: &str) {
+        let start = text.len() - text.trim_start().len();
+        let rest = &text[start..];
+        let word = &rest[..rest.find(|c: char| c.is_whitespace() || c == '#').unwrap_or(rest.len())];
+        if self.in_text || !["HEAD", "PARAGRAF", "LIST"].contains(&word.to_uppercase().as_str()) {
+            self.output.push_str(text);
+            self.in_text |= !rest.is_empty();
+            return;
+        }
+        let index = match self.variables.iter().position(|variable| variable == word) {
+            Some(index) => index,
+            None => {
+                self.variables.push(word.to_string());
+                self.variables.len() - 1
+            }
+        };
+        let after = &rest[word.len()..];
+        self.output.push_str(&format!("{}#LEMME SEE {} #MKAY{}", &text[..start], keyword_variable(word, index), after));
+        self.in_text = !after.trim().is_empty();
+    }
+
+    /// the finished source, with the variables push_text needed defined right after #HAI
+    pub fn finish(mut self) -> String {
+        let definitions: String = self.variables.iter().enumerate()
+            .map(|(i, word)| format!("#I HAZ {} #IT IZ {} #MKAY\n", keyword_variable(word, i), word))
+            .collect();
+        self.output.insert_str("#HAI\n".len(), &definitions);
+        self.output
+    }
+}
+
+//the word names its variable unless it is not an <id>, e.g. lıst which uppercases to LIST
+fn keyword_variable(word: &str, index: usize) -> String {
+    if lexer::is_identifier(word) { word.to_string() } else { format!("keyword{}", index + 1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a keyword that reads text, or text already written, keeps the word from being a keyword
+    #[test]
+    fn test_keyword_words() {
+        let mut source = SourceWriter::new();
+        source.push_str("#MAEK HEAD #GIMMEH TITLE ");
+        source.push_text("List of things");
+        source.push_str(" #MKAY #OIC\n#MAEK PARAGRAF ");
+        source.push_text("list them ");
+        source.push_str("#GIMMEH BOLD ");
+        source.push_text("head");
+        source.push_str(" #MKAY");
+        source.push_text(" Head first");
+        source.push_str(" #OIC\n#KTHXBYE\n");
+        assert_eq!(source.finish(), "#HAI\n#I HAZ list #IT IZ list #MKAY\n#I HAZ Head #IT IZ Head #MKAY\n\
+            #MAEK HEAD #GIMMEH TITLE List of things #MKAY #OIC\n\
+            #MAEK PARAGRAF #LEMME SEE list #MKAY them #GIMMEH BOLD head #MKAY #LEMME SEE Head #MKAY first #OIC\n#KTHXBYE\n");
+    }
+}
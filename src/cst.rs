@@ -0,0 +1,250 @@
+//lossless concrete syntax tree, keeps every byte of the source (whitespace, comments, the case
+//keywords were written in) so tools can rewrite a file without losing anything.
+//the parser builds the document tree from the tokens of this tree
+use crate::lexer::{Lexer, Span, Token};
+
+/// a leaf: one token exactly as written, with the whitespace before it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub token: Option<Token>, // None for text the lexer could not read
+    pub leading: String,      // trivia between the previous token and this one
+    pub text: String,
+    pub span: Span,
+}
+
+/// what a group of tokens makes up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+    Document,
+    Head,      // #MAEK HEAD ... #OIC
+    Paragraph, // #MAEK PARAGRAF ... #OIC
+    List,      // #MAEK LIST ... #OIC
+    Title,     // #GIMMEH TITLE ... #MKAY
+    Bold,
+    Italics,
+    Item,
+    Audio,
+    Video,
+    Newline,  // #GIMMEH NEWLINE
+    Define,   // #I HAZ name #IT IZ ... #MKAY
+    Variable, // #LEMME SEE name #MKAY
+}
+
+impl SyntaxKind {
+    /// blocks closed by #OIC, the rest of the groups except Newline are closed by #MKAY
+    pub fn closed_by_oic(self) -> bool {
+        matches!(self, SyntaxKind::Head | SyntaxKind::Paragraph | SyntaxKind::List)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    fn new(kind: SyntaxKind) -> Self {
+        SyntaxNode { kind, children: Vec::new() }
+    }
+
+    /// every leaf under this node, in source order
+    pub fn leaves(&self) -> Vec<&SyntaxToken> {
+        let mut leaves = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => leaves.extend(node.leaves()),
+                SyntaxElement::Token(token) => leaves.push(token),
+            }
+        }
+        leaves
+    }
+
+    /// from the start of the first leaf to the end of the last, on the first leaf's line
+    pub fn span(&self) -> Option<Span> {
+        let leaves = self.leaves();
+        let (first, last) = (leaves.first()?, leaves.last()?);
+        Some(Span { start: first.span.start, end: last.span.end, line: first.span.line })
+    }
+}
+
+/// a whole file as a syntax tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    pub root: SyntaxNode,
+    pub trailing: String,    // whitespace after the last token
+    pub errors: Vec<String>, // lexical errors, their text is kept as leaves without a token
+}
+
+impl Cst {
+    /// never fails, text the lexer cannot read becomes a leaf and an entry in errors
+    pub fn parse(source: &str) -> Cst {
+        let (tokens, errors) = Lexer::new(source).lex_recovering();
+        let mut pieces: Vec<(Option<Token>, Span)> = tokens.into_iter().map(|t| (Some(t.token), t.span)).collect();
+        pieces.extend(errors.iter().map(|(span, _)| (None, *span)));
+        pieces.sort_by_key(|(_, span)| span.start);
+
+        let mut leaves = Vec::new();
+        let mut end = 0;
+        for (token, span) in pieces {
+            leaves.push(SyntaxToken {
+                token,
+                leading: source[end..span.start].to_string(),
+                text: source[span.start..span.end].to_string(),
+                span,
+            });
+            end = span.end;
+        }
+
+        Cst {
+            root: build(leaves),
+            trailing: source[end..].to_string(),
+            errors: errors.into_iter().map(|(_, e)| e).collect(),
+        }
+    }
+
+    /// the source again, byte for byte
+    #[allow(dead_code)]
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for leaf in self.root.leaves() {
+            text.push_str(&leaf.leading);
+            text.push_str(&leaf.text);
+        }
+        text.push_str(&self.trailing);
+        text
+    }
+
+    /// the tokens the parser reads, without trivia
+    pub fn tokens(&self) -> Vec<Token> {
+        self.root.leaves().into_iter().filter_map(|leaf| leaf.token.clone()).collect()
+    }
+}
+
+//groups the leaves by their opening and closing keywords, a closer without a matching opener
+//stays a plain leaf so unbalanced files still give a tree
+fn build(leaves: Vec<SyntaxToken>) -> SyntaxNode {
+    let mut stack = vec![SyntaxNode::new(SyntaxKind::Document)];
+    let mut leaves = leaves.into_iter().peekable();
+
+    while let Some(leaf) = leaves.next() {
+        let next = leaves.peek().and_then(|l| l.token.as_ref());
+        let opens = match (&leaf.token, next) {
+            (Some(Token::Maek), Some(Token::Head)) => Some(SyntaxKind::Head),
+            (Some(Token::Maek), Some(Token::Paragraf)) => Some(SyntaxKind::Paragraph),
+            (Some(Token::Maek), Some(Token::List)) => Some(SyntaxKind::List),
+            (Some(Token::Gimmeh), Some(Token::Title)) => Some(SyntaxKind::Title),
+            (Some(Token::Gimmeh), Some(Token::Bold)) => Some(SyntaxKind::Bold),
+            (Some(Token::Gimmeh), Some(Token::Italics)) => Some(SyntaxKind::Italics),
+            (Some(Token::Gimmeh), Some(Token::Item)) => Some(SyntaxKind::Item),
+            (Some(Token::Gimmeh), Some(Token::Soundz(_))) => Some(SyntaxKind::Audio),
+            (Some(Token::Gimmeh), Some(Token::Vidz(_))) => Some(SyntaxKind::Video),
+            (Some(Token::Gimmeh), Some(Token::Newline)) => Some(SyntaxKind::Newline),
+            (Some(Token::IHaz), _) => Some(SyntaxKind::Define),
+            (Some(Token::LemmeSee), _) => Some(SyntaxKind::Variable),
+            _ => None,
+        };
+
+        if let Some(kind) = opens {
+            let mut node = SyntaxNode::new(kind);
+            node.children.push(SyntaxElement::Token(leaf));
+            if kind == SyntaxKind::Newline {
+                //#GIMMEH NEWLINE has no closing keyword
+                if let Some(newline) = leaves.next() {
+                    node.children.push(SyntaxElement::Token(newline));
+                }
+                push_child(&mut stack, SyntaxElement::Node(node));
+            } else {
+                stack.push(node);
+            }
+            continue;
+        }
+
+        let closes = match (&leaf.token, stack.last().map(|n| n.kind)) {
+            (_, Some(SyntaxKind::Document)) | (_, None) => false,
+            (Some(Token::Oic), Some(kind)) => kind.closed_by_oic(),
+            (Some(Token::Mkay), Some(kind)) => !kind.closed_by_oic(),
+            _ => false,
+        };
+        push_child(&mut stack, SyntaxElement::Token(leaf));
+        if closes {
+            close(&mut stack);
+        }
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack.pop().unwrap_or_else(|| SyntaxNode::new(SyntaxKind::Document))
+}
+
+fn push_child(stack: &mut [SyntaxNode], child: SyntaxElement) {
+    if let Some(top) = stack.last_mut() {
+        top.children.push(child);
+    }
+}
+
+fn close(stack: &mut Vec<SyntaxNode>) {
+    if let Some(node) = stack.pop() {
+        push_child(stack, SyntaxElement::Node(node));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_round_trip_every_test_file() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let mut checked = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            //every file, the .txt and .html ones are not lolcode but must still come back unchanged
+            let path = entry.unwrap().path();
+            let source = fs::read_to_string(&path).unwrap();
+            let cst = Cst::parse(&source);
+            assert_eq!(cst.text(), source, "{}", path.display());
+            for leaf in cst.root.leaves() {
+                assert_eq!(leaf.text, source[leaf.span.start..leaf.span.end]);
+                assert!(leaf.leading.trim().is_empty(), "{}: {:?}", path.display(), leaf.leading);
+            }
+            checked += 1;
+        }
+        assert!(checked >= 10);
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let cst = Cst::parse("#hai\n#MAEK PARAGRAF #I HAZ x #IT IZ 1 #MKAY #GIMMEH BOLD hi #MKAY #OIC\n#KTHXBYE\n");
+        let paragraph = match &cst.root.children[1] {
+            SyntaxElement::Node(node) => node,
+            other => panic!("expected a node, found {:?}", other),
+        };
+        assert_eq!(paragraph.kind, SyntaxKind::Paragraph);
+        let kinds: Vec<SyntaxKind> = paragraph.children.iter()
+            .filter_map(|c| match c {
+                SyntaxElement::Node(node) => Some(node.kind),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect();
+        assert_eq!(kinds, vec![SyntaxKind::Define, SyntaxKind::Bold]);
+        assert_eq!(cst.root.leaves()[0].text, "#hai");
+        assert_eq!(cst.trailing, "\n");
+    }
+
+    #[test]
+    fn test_lexical_errors_keep_their_text() {
+        let source = "#HAI #END text #KTHXBYE";
+        let cst = Cst::parse(source);
+        assert_eq!(cst.errors.len(), 1);
+        assert_eq!(cst.text(), source);
+    }
+}
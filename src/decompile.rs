@@ -0,0 +1,528 @@
+//html back to lolcode, for pages whose .lol source was lost. only the html the compiler writes
+//is understood: title, p, b, i, ul/li, br, audio, video, iframe and comments. other tags are
+//reported and their text is kept
+use crate::convert::SourceWriter;
+use crate::cst::Cst;
+use crate::fmt;
+use crate::html;
+
+//tags that never have a closing tag
+const VOID_TAGS: [&str; 8] = ["br", "source", "track", "meta", "link", "img", "hr", "input"];
+
+//tags whose content is not text for the reader
+const HIDDEN_TAGS: [&str; 3] = ["script", "style", "template"];
+
+/// the .lol source for an html page, and a warning for each tag that could not be carried
+/// over. name is the title when the page has none
+pub fn html_to_lol(source: &str, name: &str) -> (String, Vec<String>) {
+    let mut decompiler = Decompiler { output: SourceWriter::new(), warnings: Vec::new() };
+    let nodes = read_tree(source, &mut decompiler.warnings);
+
+    //<html>, <head> and <body> only group the rest
+    let mut head = Vec::new();
+    let mut body = Vec::new();
+    flatten_page(nodes, false, &mut head, &mut body);
+
+    let title = head.iter().position(|node| matches!(node, Html::Element { name, .. } if name == "title"));
+    for (i, node) in head.iter().enumerate() {
+        match node {
+            Html::Comment(text) => decompiler.comment(text),
+            Html::Element { children, line, .. } if Some(i) == title => {
+                decompiler.output.push_str("#MAEK HEAD #GIMMEH TITLE ");
+                decompiler.nodes(children, Context::Title, *line);
+                decompiler.output.push_str(" #MKAY #OIC\n");
+            }
+            Html::Element { name, line, .. } => decompiler.warn(*line, &format!("<{}> in the head cannot be written in LOLCODE; it was left out.", name)),
+            Html::Text(_) => {}
+        }
+    }
+    if title.is_none() {
+        decompiler.warn(1, &format!("A LOLCODE document starts with a title; the page has no <title>, so '{}' was used.", name));
+        decompiler.output.push_str(&format!("#MAEK HEAD #GIMMEH TITLE {} #MKAY #OIC\n", name.replace('#', "")));
+    }
+    decompiler.nodes(&body, Context::Body, 1);
+    decompiler.output.push_str("\n#KTHXBYE\n");
+    (fmt::format(&Cst::parse(&decompiler.output.finish())), decompiler.warnings)
+}
+
+//an html element, text or comment, with entities decoded
+#[derive(Debug, Clone, PartialEq)]
+enum Html {
+    Element { name: String, attributes: Vec<(String, String)>, children: Vec<Html>, line: usize },
+    Text(String),
+    Comment(String),
+}
+
+impl Html {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        match self {
+            Html::Element { attributes, .. } => attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+
+    //the first element with this name, searching the children depth first
+    fn find(&self, tag: &str) -> Option<&Html> {
+        match self {
+            Html::Element { children, .. } => children.iter().find_map(|child| match child {
+                Html::Element { name, .. } if name == tag => Some(child),
+                _ => child.find(tag),
+            }),
+            _ => None,
+        }
+    }
+}
+
+//what lolcode allows where the html is being written, the same limits the parser has
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Body,
+    Paragraph,
+    Item,
+    Span,
+    Title,
+}
+
+struct Decompiler {
+    output: SourceWriter,
+    warnings: Vec<String>,
+}
+
+impl Decompiler {
+    fn warn(&mut self, line: usize, message: &str) {
+        self.warnings.push(format!("Warning: line {}: {}", line, message));
+    }
+
+    fn comment(&mut self, text: &str) {
+        self.output.push_str(&format!(" #OBTW {} #TLDR ", text.trim().replace('#', "")));
+    }
+
+    fn nodes(&mut self, nodes: &[Html], context: Context, line: usize) {
+        for node in nodes {
+            match node {
+                Html::Text(text) => self.text(text, line),
+                Html::Comment(text) => self.comment(text),
+                Html::Element { name, children, line, .. } => self.element(node, name, children, context, *line),
+            }
+        }
+    }
+
+    fn element(&mut self, node: &Html, name: &str, children: &[Html], context: Context, line: usize) {
+        match (name, context) {
+            (_, Context::Title) => self.nodes(children, context, line),
+            ("b" | "i", Context::Span) => {
+                self.warn(line, &format!("<{}> cannot be nested in bold or italics in LOLCODE; only its text was kept.", name));
+                self.nodes(children, context, line);
+            }
+            ("b" | "i", _) => {
+                let keyword = if name == "b" { "BOLD" } else { "ITALICS" };
+                self.output.push_str(&format!(" #GIMMEH {} ", keyword));
+                self.nodes(children, Context::Span, line);
+                self.output.push_str(" #MKAY ");
+            }
+            ("br", Context::Body | Context::Paragraph) => self.output.push_str(" #GIMMEH NEWLINE "),
+            ("br", _) => {
+                self.warn(line, "<br> can only be written in a paragraph in LOLCODE; it became a space.");
+                self.output.push(' ');
+            }
+            ("p", Context::Body) => {
+                self.output.push_str("\n#MAEK PARAGRAF ");
+                self.nodes(children, Context::Paragraph, line);
+                self.output.push_str(" #OIC\n");
+            }
+            ("ul", Context::Body | Context::Paragraph) => self.list(children, line),
+            ("audio" | "video" | "iframe", Context::Body | Context::Paragraph) => match media(node) {
+                Some(media) => self.output.push_str(&format!(" {} ", media)),
+                None => self.warn(line, &format!("<{}> has no address; it was left out.", name)),
+            },
+            ("audio" | "video" | "iframe", _) => {
+                self.warn(line, &format!("<{}> can only be written in a paragraph in LOLCODE; it was left out.", name));
+            }
+            ("p" | "ul" | "li", _) => {
+                self.warn(line, &format!("<{}> cannot be written here in LOLCODE; only its text was kept.", name));
+                self.nodes(children, context, line);
+            }
+            (name, _) if HIDDEN_TAGS.contains(&name) => {
+                self.warn(line, &format!("<{}> cannot be written in LOLCODE; it was left out.", name));
+            }
+            _ => {
+                self.warn(line, &format!("<{}> is not part of the HTML the compiler writes; only its text was kept.", name));
+                self.nodes(children, context, line);
+            }
+        }
+    }
+
+    //items of a nested list join the outer one, lolcode lists cannot be nested
+    fn list(&mut self, children: &[Html], line: usize) {
+        let mut items = Vec::new();
+        self.list_items(children, &mut items, line);
+        if !items.iter().any(|item| matches!(item, Html::Element { .. })) {
+            self.warn(line, "<ul> has no items; it was left out.");
+            return;
+        }
+        self.output.push_str("\n#MAEK LIST\n");
+        for item in items {
+            match item {
+                Html::Element { children, line, .. } => {
+                    self.output.push_str("#GIMMEH ITEM ");
+                    self.nodes(&children, Context::Item, line);
+                    self.output.push_str(" #MKAY\n");
+                }
+                Html::Comment(text) => self.comment(&text),
+                Html::Text(_) => {}
+            }
+        }
+        self.output.push_str("#OIC\n");
+    }
+
+    fn list_items(&mut self, children: &[Html], items: &mut Vec<Html>, line: usize) {
+        for child in children {
+            match child {
+                Html::Element { name, children, line, .. } if name == "li" => {
+                    let (nested, content): (Vec<Html>, Vec<Html>) = children.iter().cloned()
+                        .partition(|node| matches!(node, Html::Element { name, .. } if name == "ul"));
+                    items.push(Html::Element { name: name.clone(), attributes: Vec::new(), children: content, line: *line });
+                    for list in nested {
+                        self.warn(*line, "Nested lists cannot be written in LOLCODE; the inner items joined the outer list.");
+                        if let Html::Element { children, line, .. } = list {
+                            self.list_items(&children, items, line);
+                        }
+                    }
+                }
+                Html::Comment(_) => items.push(child.clone()),
+                Html::Text(text) if text.trim().is_empty() => {}
+                _ => self.warn(line, "Only <li> can be written in a list in LOLCODE; the rest was left out."),
+            }
+        }
+    }
+
+    //html collapses whitespace, and # always starts a keyword in lolcode
+    fn text(&mut self, text: &str, line: usize) {
+        if text.contains('#') {
+            self.warn(line, "'#' cannot be written in LOLCODE text; it was left out.");
+        }
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if text.starts_with(char::is_whitespace) {
+            self.output.push(' ');
+        }
+        self.output.push_text(&words.join(" ").replace('#', ""));
+        if text.ends_with(char::is_whitespace) && !words.is_empty() {
+            self.output.push(' ');
+        }
+    }
+}
+
+//#GIMMEH SOUNDZ/VIDZ with the options the player was written with, see media::render_audio
+//and media::render_video for the other direction
+fn media(node: &Html) -> Option<String> {
+    let Html::Element { name, .. } = node else { return None };
+    let mut options = Vec::new();
+    let address = if name == "iframe" {
+        embed_address(node.attribute("src")?, &mut options)
+    } else {
+        for flag in ["autoplay", "loop", "muted"] {
+            if node.attribute(flag).is_some() {
+                options.push(flag.to_string());
+            }
+        }
+        let src = node.find("source").and_then(|source| source.attribute("src")).or(node.attribute("src"))?;
+        //the start time is written as a media fragment
+        match src.rsplit_once("#t=") {
+            Some((address, start)) if start.parse::<u32>().is_ok() => {
+                options.push(format!("start={}", start));
+                address.to_string()
+            }
+            _ => src.to_string(),
+        }
+    };
+    for dimension in ["width", "height"] {
+        if let Some(value) = node.attribute(dimension) {
+            options.push(format!("{}={}", dimension, value));
+        }
+    }
+    if let Some(captions) = node.find("track").and_then(|track| track.attribute("src")) {
+        options.push(format!("captions={}", captions));
+    }
+    let keyword = if name == "audio" { "SOUNDZ" } else { "VIDZ" };
+    let options: String = options.iter().map(|option| format!(" {}", option)).collect();
+    Some(format!("#GIMMEH {} {}{} #MKAY", keyword, address, options))
+}
+
+//embedded players carry their options in the query (and vimeo's start time in the fragment),
+//they are taken back out so the address renders to the same frame again
+fn embed_address(src: &str, options: &mut Vec<String>) -> String {
+    let (src, fragment) = match src.split_once('#') {
+        Some((src, fragment)) => (src, Some(fragment)),
+        None => (src, None),
+    };
+    let (base, query) = src.split_once('?').unwrap_or((src, ""));
+    let mut kept = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        match pair.split_once('=') {
+            Some(("autoplay", "1")) => options.push("autoplay".to_string()),
+            Some(("loop", "1")) => options.push("loop".to_string()),
+            Some(("mute" | "muted", "1")) => options.push("muted".to_string()),
+            Some(("start", start)) if start.parse::<u32>().is_ok() => options.push(format!("start={}", start)),
+            //youtube loops a video by making it its own playlist
+            Some(("playlist", _)) if query.contains("loop=1") => {}
+            _ => kept.push(pair),
+        }
+    }
+    match fragment.and_then(|f| f.strip_prefix("t=")).and_then(|t| t.strip_suffix('s')) {
+        Some(start) if start.parse::<u32>().is_ok() => options.push(format!("start={}", start)),
+        _ => {}
+    }
+    //the player options are written in the order the compiler reads them
+    let order = ["autoplay", "loop", "muted", "start"];
+    options.sort_by_key(|option| order.iter().position(|o| option.starts_with(o)));
+    let mut address = base.to_string();
+    if !kept.is_empty() {
+        address.push('?');
+        address.push_str(&kept.join("&"));
+    }
+    address
+}
+
+//sorts the top level of the page into the head and the body
+fn flatten_page(nodes: Vec<Html>, in_head: bool, head: &mut Vec<Html>, body: &mut Vec<Html>) {
+    for node in nodes {
+        match node {
+            Html::Element { name, children, .. } if name == "html" => flatten_page(children, in_head, head, body),
+            Html::Element { name, children, .. } if name == "head" => flatten_page(children, true, head, body),
+            Html::Element { name, children, .. } if name == "body" => body.extend(children),
+            Html::Element { ref name, .. } if name == "title" || in_head => head.push(node),
+            Html::Comment(_) if body.is_empty() => head.push(node),
+            Html::Text(ref text) if text.trim().is_empty() && (in_head || body.is_empty()) => {}
+            node => body.push(node),
+        }
+    }
+}
+
+//---- reading ----
+
+//an element whose end tag has not been read yet
+struct Open {
+    name: String,
+    attributes: Vec<(String, String)>,
+    line: usize,
+    children: Vec<Html>,
+}
+
+//a forgiving reader: an end tag closes the innermost open element with its name, an end tag
+//with no open element is ignored and whatever is still open at the end is closed
+fn read_tree(source: &str, warnings: &mut Vec<String>) -> Vec<Html> {
+    //open elements, each with the children read so far
+    let mut stack = vec![Open { name: String::new(), attributes: Vec::new(), line: 1, children: Vec::new() }];
+    let mut rest = source;
+    let mut line = 1;
+    while !rest.is_empty() {
+        let (consumed, event) = read_event(rest);
+        let event_line = line;
+        line += rest[..consumed].matches('\n').count();
+        rest = &rest[consumed..];
+        match event {
+            Event::Text(text) => stack.last_mut().unwrap_or_else(|| unreachable!()).children.push(Html::Text(html::unescape(&text))),
+            Event::Comment(text) => stack.last_mut().unwrap_or_else(|| unreachable!()).children.push(Html::Comment(text)),
+            Event::Doctype => {}
+            Event::Start(name, attributes) => {
+                //a new item ends the one before it
+                if name == "li" && stack.last().is_some_and(|open| open.name == "li") {
+                    close(&mut stack);
+                }
+                if HIDDEN_TAGS.contains(&name.as_str()) {
+                    //skip to the end tag so what is inside is not read as markup
+                    let end = rest.to_ascii_lowercase().find(&format!("</{}", name)).unwrap_or(rest.len());
+                    line += rest[..end].matches('\n').count();
+                    rest = &rest[end..];
+                }
+                let void = VOID_TAGS.contains(&name.as_str());
+                stack.push(Open { name, attributes, line: event_line, children: Vec::new() });
+                if void {
+                    close(&mut stack);
+                }
+            }
+            Event::End(name) => match stack.iter().rposition(|open| open.name == name) {
+                Some(i) if i > 0 => {
+                    while stack.len() > i {
+                        close(&mut stack);
+                    }
+                }
+                _ if VOID_TAGS.contains(&name.as_str()) => {}
+                _ => warnings.push(format!("Warning: line {}: </{}> closes nothing; it was left out.", event_line, name)),
+            },
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack.pop().map(|root| root.children).unwrap_or_default()
+}
+
+fn close(stack: &mut Vec<Open>) {
+    if let Some(Open { name, attributes, line, children }) = stack.pop()
+        && let Some(parent) = stack.last_mut()
+    {
+        parent.children.push(Html::Element { name, attributes, children, line });
+    }
+}
+
+enum Event {
+    Text(String),
+    Comment(String),
+    Doctype,
+    Start(String, Vec<(String, String)>),
+    End(String),
+}
+
+//the next tag, comment or run of text, with how many bytes it took
+fn read_event(rest: &str) -> (usize, Event) {
+    if let Some(comment) = rest.strip_prefix("<!--") {
+        let end = comment.find("-->").unwrap_or(comment.len());
+        let consumed = (4 + end + 3).min(rest.len());
+        return (consumed, Event::Comment(comment[..end].trim().to_string()));
+    }
+    let is_tag = rest.starts_with('<')
+        && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+    let tag_end = rest.find('>');
+    match tag_end {
+        Some(end) if is_tag => {
+            let tag = &rest[1..end];
+            let event = if tag.starts_with('!') {
+                Event::Doctype
+            } else if let Some(name) = tag.strip_prefix('/') {
+                Event::End(name.trim().to_lowercase())
+            } else {
+                let tag = tag.trim_end_matches('/');
+                let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+                Event::Start(tag[..name_end].to_lowercase(), attributes(&tag[name_end..]))
+            };
+            (end + 1, event)
+        }
+        _ => {
+            //text runs to the next tag, a lone < is text
+            let first = rest.chars().next().map_or(0, char::len_utf8);
+            let end = rest[first..].find('<').map_or(rest.len(), |i| i + first);
+            (end, Event::Text(rest[..end].to_string()))
+        }
+    }
+}
+
+//name="value", name='value', name=value and bare names
+fn attributes(text: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let name = rest[..name_end].to_lowercase();
+        rest = rest[name_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, remaining) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map_or(value.len(), |i| i + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = remaining.trim_start();
+                html::unescape(value)
+            }
+            None => String::new(),
+        };
+        attributes.push((name, value));
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::fs;
+    use std::path::Path;
+
+    fn compile(source: &str, keep_comments: bool) -> String {
+        html::render(&testing::parse(source), keep_comments)
+    }
+
+    //tests/TestN.html is compiler output, it comes back as source that compiles to the same page
+    #[test]
+    fn test_round_trip_test_pages() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let mut checked = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "html") {
+                continue;
+            }
+            let page = fs::read_to_string(&path).unwrap();
+            let (lol, warnings) = html_to_lol(&page, "test");
+            assert!(warnings.is_empty(), "{}: {:?}", path.display(), warnings);
+            assert_eq!(compile(&lol, false), page, "{}\n{}", path.display(), lol);
+            checked += 1;
+        }
+        assert!(checked >= 6);
+    }
+
+    #[test]
+    fn test_media_lists_and_comments_round_trip() {
+        let source = "#HAI #OBTW made by hand #TLDR #MAEK HEAD #GIMMEH TITLE Fish &amp; <Chips> #MKAY #OIC
+            #MAEK PARAGRAF #GIMMEH ITALICS one #MKAY two #GIMMEH NEWLINE three
+            #MAEK LIST #GIMMEH ITEM a #MKAY #OBTW between #TLDR #GIMMEH ITEM #GIMMEH BOLD b #MKAY c #MKAY #OIC #OIC
+            #GIMMEH SOUNDZ theme.mp3 autoplay loop start=5 captions=theme.vtt #MKAY
+            #GIMMEH VIDZ https://www.youtube.com/watch?v=dQw4w9WgXcQ autoplay loop muted start=30 width=640 #MKAY
+            #GIMMEH VIDZ https://vimeo.com/76979871 muted start=12 #MKAY
+            #GIMMEH VIDZ clip.webm height=200 #MKAY #KTHXBYE";
+        let page = compile(source, true);
+        let (lol, warnings) = html_to_lol(&page, "test");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(compile(&lol, true), page, "{}", lol);
+        assert!(lol.contains("#GIMMEH SOUNDZ theme.mp3 autoplay loop start=5 captions=theme.vtt #MKAY"), "{}", lol);
+        assert!(lol.contains("#OBTW made by hand #TLDR"), "{}", lol);
+    }
+
+    #[test]
+    fn test_non_ascii_text_round_trips() {
+        let page = compile("#HAI #MAEK HEAD #GIMMEH TITLE Café #MKAY #OIC #MAEK PARAGRAF été #GIMMEH BOLD 日本 #MKAY ü #OIC #KTHXBYE", false);
+        let (lol, warnings) = html_to_lol(&page, "test");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(compile(&lol, false), page, "{}", lol);
+    }
+
+    //text starting with a word the lexer reads as a keyword still compiles to the same page
+    #[test]
+    fn test_keyword_words_in_text() {
+        let page = "<html><head><title>List</title></head><body>\n<p>List of things <b>x</b> head<br>\nParagraf two</p>\n<ul>\n<li>HEAD first</li>\n<li>list</li>\n</ul>\n</body></html>\n";
+        let (lol, warnings) = html_to_lol(page, "test");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let html = compile(&lol, false);
+        assert!(html.contains("<p>List of things <b>x</b> head<br>\nParagraf two</p>"), "{}\n{}", lol, html);
+        assert!(html.contains("<li>HEAD first</li>\n<li>list</li>"), "{}\n{}", lol, html);
+        assert_eq!(html_to_lol(&html, "test").0, lol);
+    }
+
+    #[test]
+    fn test_unsupported_tags_are_reported() {
+        let page = "<html><body>\n<h1>Big</h1>\n<p>Issue #42 <a href=\"x.html\">link</a></p>\n<ul><li>a<ul><li>b</li></ul></li></ul>\n<script>if (a < b) {}</script>\n</div></body></html>";
+        let (lol, warnings) = html_to_lol(page, "notes");
+        compile(&lol, false);
+        let expected = [
+            "line 6: </div> closes nothing",
+            "line 1: A LOLCODE document starts with a title",
+            "line 2: <h1> is not part",
+            "line 3: '#'",
+            "line 3: <a> is not part",
+            "line 4: Nested lists",
+            "line 5: <script> cannot be written",
+        ];
+        assert_eq!(warnings.len(), expected.len(), "{:?}", warnings);
+        for (warning, expected) in warnings.iter().zip(expected) {
+            assert!(warning.starts_with(&format!("Warning: {}", expected)), "{}", warning);
+        }
+        assert!(lol.contains("#GIMMEH ITEM a #MKAY\n    #GIMMEH ITEM b #MKAY"), "{}", lol);
+    }
+}
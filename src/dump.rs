@@ -0,0 +1,138 @@
+//--emit tokens: what the lexer made of a file, one row per token with its span and the source
+//it was read from, so grammar problems can be looked at without writing a test.
+//text the lexer could not read shows up as an error row and the rest of the file is still listed
+use crate::json::{self, Value};
+use crate::lexer::{Lexer, Span, Token};
+
+//longer tokens (comments mostly) are cut short in the table, the source column has them in full
+const TOKEN_WIDTH: usize = 40;
+
+//a token or a lexical error, in source order
+struct Row {
+    token: Option<Token>,
+    error: Option<String>,
+    span: Span,
+}
+
+fn rows(source: &str) -> Vec<Row> {
+    let (tokens, errors) = Lexer::new(source).lex_recovering();
+    let mut rows: Vec<Row> = tokens.into_iter().map(|t| Row { token: Some(t.token), error: None, span: t.span }).collect();
+    rows.extend(errors.into_iter().map(|(span, e)| Row { token: None, error: Some(e), span }));
+    rows.sort_by_key(|row| row.span.start);
+    rows
+}
+
+/// true when the file has lexical errors, the dump still lists everything else
+pub fn has_errors(source: &str) -> bool {
+    !Lexer::new(source).lex_recovering().1.is_empty()
+}
+
+/// a table with a header, columns line up
+pub fn table(source: &str) -> String {
+    let cells: Vec<[String; 4]> = rows(source).into_iter()
+        .map(|row| {
+            //the message goes after the source so it does not widen the token column
+            let text = format!("{:?}", &source[row.span.start..row.span.end]);
+            let (token, text) = match (&row.token, &row.error) {
+                (Some(token), _) => (shorten(&format!("{:?}", token)), text),
+                (None, Some(e)) => ("error".to_string(), format!("{}  <- {}", text, e.replace('\n', " "))),
+                (None, None) => (String::new(), text),
+            };
+            let span = format!("{}..{}", row.span.start, row.span.end);
+            [row.span.line.to_string(), span, token, text]
+        })
+        .collect();
+
+    let header = ["line", "span", "token", "source"];
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut output = String::new();
+    for row in std::iter::once(header.map(str::to_string)).chain(cells) {
+        //the line number is right aligned, the source column is last and not padded
+        let line = format!("{:>w0$}  {:<w1$}  {:<w2$}  {}", row[0], row[1], row[2], row[3], w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+/// one json object per line: line, start, end, token (the variant name, or "error"), value
+/// (the text a token carries, or the error message) and the source text
+pub fn json_lines(source: &str) -> String {
+    let mut output = String::new();
+    for row in rows(source) {
+        let (name, value) = match (&row.token, &row.error) {
+            (Some(token), _) => (token_name(token), token_value(token).map(str::to_string)),
+            (None, error) => ("error".to_string(), error.clone()),
+        };
+        let fields = [
+            ("line", Value::Number(row.span.line as f64)),
+            ("start", Value::Number(row.span.start as f64)),
+            ("end", Value::Number(row.span.end as f64)),
+            ("token", Value::String(name)),
+            ("value", value.map_or(Value::Null, Value::String)),
+            ("text", Value::String(source[row.span.start..row.span.end].to_string())),
+        ];
+        let fields: Vec<String> = fields.iter()
+            .map(|(key, value)| format!("{}: {}", json::quote(key), value.to_pretty_string()))
+            .collect();
+        output.push_str(&format!("{{{}}}\n", fields.join(", ")));
+    }
+    output
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() <= TOKEN_WIDTH {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(TOKEN_WIDTH - 3).collect();
+    short.push_str("...");
+    short
+}
+
+//the variant name, e.g. VarDef for VarDef("answer")
+fn token_name(token: &Token) -> String {
+    let debug = format!("{:?}", token);
+    debug.split('(').next().unwrap_or_default().to_string()
+}
+
+fn token_value(token: &Token) -> Option<&str> {
+    match token {
+        Token::Soundz(s) | Token::Vidz(s) | Token::VarDef(s) | Token::VarVal(s) | Token::VarUse(s)
+        | Token::Text(s) | Token::Comment(s) => Some(s),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_and_json_lines() {
+        let source = "#HAI #I HAZ answer #IT IZ 42 #MKAY\n#END #KTHXBYE";
+        assert!(has_errors(source));
+        assert_eq!(table(source), "\
+line  span    token             source
+   1  0..4    Hai               \"#HAI\"
+   1  5..11   IHaz              \"#I HAZ\"
+   1  12..18  VarDef(\"answer\")  \"answer\"
+   1  19..25  ItIz              \"#IT IZ\"
+   1  26..28  VarVal(\"42\")      \"42\"
+   1  29..34  Mkay              \"#MKAY\"
+   2  35..39  error             \"#END\"  <- Unknown annotation '#END' on line 2 (did you mean `#OIC`?)
+   2  40..48  Kthxbye           \"#KTHXBYE\"
+");
+
+        let lines: Vec<String> = json_lines(source).lines().map(str::to_string).collect();
+        assert_eq!(lines[2], "{\"line\": 1, \"start\": 12, \"end\": 18, \"token\": \"VarDef\", \"value\": \"answer\", \"text\": \"answer\"}");
+        let error = json::parse(&lines[6]).unwrap();
+        assert_eq!(error.get("token").and_then(Value::as_str), Some("error"));
+        assert_eq!(error.get("text").and_then(Value::as_str), Some("#END"));
+        assert!(lines.iter().all(|line| json::parse(line).is_ok()));
+    }
+}
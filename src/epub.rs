@@ -0,0 +1,285 @@
+//epub 3 export: each chapter is the html renderer's page made into xhtml, in a folder of its own
+//with the local media it plays, and a package document and table of contents tie them together
+use crate::assets::Asset;
+use crate::html;
+use crate::media;
+use crate::zip::{self, ZipWriter};
+use std::fs;
+
+const PACKAGE: &str = "OEBPS/content.opf";
+
+/// a compiled chapter: its title, the page the html renderer wrote and the local media it uses
+pub struct Chapter {
+    pub title: String,
+    pub html: String,
+    pub assets: Vec<Asset>,
+}
+
+/// the .epub file for the chapters, in order. modified is when the book was made, see timestamp
+pub fn build(title: &str, chapters: &[Chapter], modified: &str) -> Result<Vec<u8>, String> {
+    let mut archive = ZipWriter::new();
+    //readers recognise the format by this first entry, stored uncompressed
+    archive.add("mimetype", b"application/epub+zip")?;
+    archive.add("META-INF/container.xml", container().as_bytes())?;
+
+    let mut manifest = vec![r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#.to_string()];
+    let mut spine = Vec::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        let folder = format!("chapter{}", i + 1);
+        let page = xhtml(&chapter.html, &chapter.title);
+        archive.add(&format!("OEBPS/{}/index.xhtml", folder), page.as_bytes())?;
+
+        //media on the web is played from there, the reader has to be told
+        let remote = ["src=\"http://", "src=\"https://"].iter().any(|src| page.contains(src));
+        let properties = if remote { " properties=\"remote-resources\"" } else { "" };
+        manifest.push(format!(r#"<item id="{}" href="{}/index.xhtml" media-type="application/xhtml+xml"{}/>"#, folder, folder, properties));
+        spine.push(format!(r#"<itemref idref="{}"/>"#, folder));
+
+        for (j, asset) in chapter.assets.iter().enumerate() {
+            let bytes = fs::read(&asset.source)
+                .map_err(|e| format!("Code Generation Error: Could not read media file '{}': {}", asset.source.display(), e))?;
+            archive.add(&format!("OEBPS/{}/{}", folder, asset.output_path()), &bytes)?;
+            manifest.push(format!(
+                r#"<item id="{}-media{}" href="{}/{}" media-type="{}"/>"#,
+                folder, j + 1, folder, html::escape_attribute(&asset.output), media_type(&asset.output)
+            ));
+        }
+    }
+    archive.add("OEBPS/nav.xhtml", navigation(title, chapters).as_bytes())?;
+
+    //the same chapters give the same identifier, so a rebuilt book replaces the old one
+    let texts: String = chapters.iter().map(|chapter| chapter.html.as_str()).collect();
+    let identifier = format!("urn:lolcode:{:08x}", zip::crc32(format!("{}\n{}", title, texts).as_bytes()));
+    let package = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="book-id">{}</dc:identifier>
+<dc:title>{}</dc:title>
+<dc:language>en</dc:language>
+<meta property="dcterms:modified">{}</meta>
+</metadata>
+<manifest>
+{}
+</manifest>
+<spine>
+{}
+</spine>
+</package>
+"#,
+        identifier, html::escape_text(title), modified, manifest.join("\n"), spine.join("\n")
+    );
+    archive.add(PACKAGE, package.as_bytes())?;
+    archive.finish()
+}
+
+fn container() -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+<rootfiles>
+<rootfile full-path="{}" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#,
+        PACKAGE
+    )
+}
+
+//the table of contents, one entry per chapter title
+fn navigation(title: &str, chapters: &[Chapter]) -> String {
+    let entries: Vec<String> = chapters.iter().enumerate()
+        .map(|(i, chapter)| format!("<li><a href=\"chapter{}/index.xhtml\">{}</a></li>", i + 1, html::escape_text(&chapter.title)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{0}</title>
+</head>
+<body>
+<nav epub:type="toc">
+<h1>{0}</h1>
+<ol>
+{1}
+</ol>
+</nav>
+</body>
+</html>
+"#,
+        html::escape_text(title), entries.join("\n")
+    )
+}
+
+fn media_type(path: &str) -> &'static str {
+    if let Some(mime) = media::audio_mime_type(path).or_else(|| media::video_mime_type(path)) {
+        return mime;
+    }
+    match media::extension(path).as_deref() {
+        Some("vtt") => "text/vtt",
+        _ => "application/octet-stream",
+    }
+}
+
+//html5 void and boolean attribute forms are not xml
+const VOID_TAGS: [&str; 4] = ["br", "source", "track", "img"];
+
+/// a page from the html renderer as xhtml: an xml declaration and namespace, void tags closed,
+/// bare attributes given a value, and a paragraph holding a list (which html does not allow
+/// but the renderer writes for a list inside #MAEK PARAGRAF) turned into a div
+pub fn xhtml(page: &str, title: &str) -> String {
+    let mut page = page.strip_prefix("<!DOCTYPE html>\n<html>\n").unwrap_or(page).to_string();
+    //xhtml needs a title in a head
+    if !page.contains("<head>") {
+        page = page.replacen("<body>", &format!("<head>\n<title>{}</title>\n</head>\n<body>", html::escape_text(title)), 1);
+    }
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\">\n");
+
+    //the renderer never nests paragraphs, so each <p> ends at the next </p>
+    let mut rest = page.as_str();
+    let mut divs = Vec::new();
+    let mut at = 0;
+    while let Some(start) = rest[at..].find("<p>").map(|i| at + i) {
+        let end = rest[start..].find("</p>").map_or(rest.len(), |i| start + i);
+        divs.push(rest[start..end].contains("<ul>"));
+        at = end;
+    }
+    let mut paragraphs = divs.into_iter();
+    let mut in_div = Vec::new();
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        //comments are the same in xml, and the renderer already keeps -- out of them
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |i| i + 3);
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        //attribute values are escaped, so the first > ends the tag
+        let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+        let tag = rest[1..end].trim_end_matches('>');
+        rest = &rest[end..];
+        match tag {
+            "p" => {
+                let div = paragraphs.next().unwrap_or(false);
+                in_div.push(div);
+                output.push_str(if div { "<div>" } else { "<p>" });
+            }
+            "/p" => output.push_str(if in_div.pop().unwrap_or(false) { "</div>" } else { "</p>" }),
+            _ if tag.starts_with('/') => output.push_str(&format!("<{}>", tag)),
+            _ => output.push_str(&xml_tag(tag)),
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+//name key="value" bare ... as an xml start tag. frameborder is dropped, it is not html5
+fn xml_tag(tag: &str) -> String {
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    let mut output = format!("<{}", name);
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+        let value = match rest.strip_prefix("=\"") {
+            Some(quoted) => {
+                let value_end = quoted.find('"').unwrap_or(quoted.len());
+                rest = quoted.get(value_end + 1..).unwrap_or("");
+                quoted[..value_end].to_string()
+            }
+            None => key.to_string(),
+        };
+        rest = rest.trim_start();
+        if key != "frameborder" {
+            output.push_str(&format!(" {}=\"{}\"", key, value));
+        }
+    }
+    output.push_str(if VOID_TAGS.contains(&name) { " />" } else { ">" });
+    output
+}
+
+/// seconds since 1970 as the utc date and time dcterms:modified takes, e.g. 2024-05-01T12:00:00Z
+pub fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    //days to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp(1700000000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn test_xhtml() {
+        let source = "#HAI #MAEK HEAD #GIMMEH TITLE Fish & Chips #MKAY #OIC
+            #MAEK PARAGRAF one #GIMMEH NEWLINE two #MAEK LIST #GIMMEH ITEM a #MKAY #OIC #OIC
+            #MAEK PARAGRAF three #OIC
+            #GIMMEH SOUNDZ theme.mp3 autoplay #MKAY #GIMMEH VIDZ https://vimeo.com/76979871 #MKAY #KTHXBYE";
+        let document = testing::parse(source);
+        let page = xhtml(&html::render(&document, false), "unused");
+        assert_eq!(page, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE html>
+<html xmlns=\"http://www.w3.org/1999/xhtml\">
+<head>
+<title>Fish &amp; Chips</title>
+</head>
+<body>
+<div>one<br />
+two
+<ul>
+<li>a</li>
+</ul>
+</div>
+<p>three</p>
+<audio controls=\"controls\" autoplay=\"autoplay\"><source src=\"theme.mp3\" type=\"audio/mpeg\" /></audio>
+<iframe src=\"https://player.vimeo.com/video/76979871\" allowfullscreen=\"allowfullscreen\"></iframe>
+</body>
+</html>
+");
+
+        //a page with no head gets one
+        let document = testing::parse("#HAI just text #KTHXBYE");
+        assert!(xhtml(&html::render(&document, false), "Notes").contains("<head>\n<title>Notes</title>\n</head>\n<body>\njust text"));
+    }
+
+    #[test]
+    fn test_build() {
+        let chapters = [
+            Chapter { title: "One & Two".to_string(), html: "<!DOCTYPE html>\n<html>\n<body>\n<p>a</p>\n</body>\n</html>\n".to_string(), assets: Vec::new() },
+            Chapter { title: "Three".to_string(), html: "<!DOCTYPE html>\n<html>\n<body>\n<audio controls><source src=\"https://example.com/a.mp3\" type=\"audio/mpeg\"></audio>\n</body>\n</html>\n".to_string(), assets: Vec::new() },
+        ];
+        let book = build("Book", &chapters, "2024-05-01T12:00:00Z").unwrap();
+        let text = String::from_utf8_lossy(&book);
+        assert_eq!(&book[30..58], b"mimetypeapplication/epub+zip");
+        assert!(text.contains("<rootfile full-path=\"OEBPS/content.opf\""));
+        assert!(text.contains("<li><a href=\"chapter1/index.xhtml\">One &amp; Two</a></li>\n<li><a href=\"chapter2/index.xhtml\">Three</a></li>"));
+        assert!(text.contains("<item id=\"chapter2\" href=\"chapter2/index.xhtml\" media-type=\"application/xhtml+xml\" properties=\"remote-resources\"/>"));
+        assert!(text.contains("<spine>\n<itemref idref=\"chapter1\"/>\n<itemref idref=\"chapter2\"/>\n</spine>"));
+        assert!(text.contains("<meta property=\"dcterms:modified\">2024-05-01T12:00:00Z</meta>"));
+        assert_eq!(build("Book", &chapters, "2024-05-01T12:00:00Z").unwrap(), book);
+    }
+}
@@ -0,0 +1,268 @@
+//canonical formatting of a .lol file from its syntax tree: uppercase keywords, blocks indented
+//inside #MAEK ... #OIC, one block per line and one space before #MKAY.
+//only whitespace the whitespace model ignores is changed, comments are kept as written
+use crate::cst::{Cst, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::lexer::Token;
+
+const INDENT: &str = "    ";
+
+/// the file reprinted canonically, formatting the result again changes nothing
+pub fn format(cst: &Cst) -> String {
+    let mut formatter = Formatter::default();
+    formatter.block(&cst.root.children, 0);
+    formatter.flush();
+    let mut output = formatter.lines.join("\n");
+    output.push('\n');
+    output
+}
+
+#[derive(Default)]
+struct Formatter {
+    lines: Vec<String>,
+    current: String,
+    indent: usize,
+    tail: String,        // whitespace at the end of the last leaf, not yet placed
+    force_space: bool,   // the last keyword is always followed by a space, e.g. #GIMMEH BOLD
+    block_start: bool,   // no blank line right after #MAEK ...
+}
+
+impl Formatter {
+    //children of the document or of a #MAEK block, blocks get their own lines
+    fn block(&mut self, children: &[SyntaxElement], indent: usize) {
+        for child in children {
+            self.indent = indent;
+            match child {
+                SyntaxElement::Node(node) if node.kind.closed_by_oic() => self.maek_block(node, indent),
+                SyntaxElement::Node(node) if own_line(node.kind) => {
+                    self.start_line(child);
+                    self.inline(child);
+                    self.flush();
+                }
+                SyntaxElement::Node(node) if node.kind == SyntaxKind::Newline => {
+                    self.inline_run(child);
+                    self.flush();
+                }
+                SyntaxElement::Token(leaf) if matches!(leaf.token, Some(Token::Hai) | Some(Token::Kthxbye)) => {
+                    self.start_line(child);
+                    self.inline(child);
+                    self.flush();
+                }
+                //a comment written on its own line stays on its own line
+                SyntaxElement::Token(leaf) if matches!(leaf.token, Some(Token::Comment(_))) && self.peek_gap(child).contains('\n') => {
+                    self.start_line(child);
+                    self.inline(child);
+                    self.flush();
+                }
+                _ => self.inline_run(child),
+            }
+        }
+    }
+
+    //#MAEK X, the contents one level deeper, #OIC
+    fn maek_block(&mut self, node: &SyntaxNode, indent: usize) {
+        let children = &node.children;
+        let opening = children.len().min(2);
+        let closing = match children.last() {
+            Some(SyntaxElement::Token(leaf)) if children.len() > opening && leaf.token == Some(Token::Oic) => 1,
+            _ => 0,
+        };
+
+        if let Some(first) = children.first() {
+            self.start_line(first);
+        }
+        for child in &children[..opening] {
+            self.inline(child);
+        }
+        self.flush();
+        self.block_start = true;
+        self.block(&children[opening..children.len() - closing], indent + 1);
+        self.flush();
+        self.indent = indent;
+        if closing == 1 {
+            self.inline(&children[children.len() - 1]);
+            self.flush();
+        }
+    }
+
+    //inline content continues the current line, a new line may follow a blank line
+    fn inline_run(&mut self, element: &SyntaxElement) {
+        if self.current.is_empty() {
+            self.blank_line_if_needed(element);
+        }
+        self.inline(element);
+    }
+
+    fn start_line(&mut self, element: &SyntaxElement) {
+        self.flush();
+        self.blank_line_if_needed(element);
+    }
+
+    //one blank line where the source had at least one
+    fn blank_line_if_needed(&mut self, element: &SyntaxElement) {
+        let blank = self.peek_gap(element).matches('\n').count() >= 2;
+        if blank && !self.block_start && self.lines.last().is_some_and(|l| !l.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    fn inline(&mut self, element: &SyntaxElement) {
+        match element {
+            SyntaxElement::Node(node) => {
+                for child in &node.children {
+                    self.inline(child);
+                }
+            }
+            SyntaxElement::Token(leaf) => self.leaf(leaf),
+        }
+    }
+
+    fn leaf(&mut self, leaf: &SyntaxToken) {
+        let gap = self.take_gap(leaf);
+        let forced = std::mem::take(&mut self.force_space);
+        let space = forced || !gap.is_empty();
+        match &leaf.token {
+            Some(Token::Text(text)) | Some(Token::VarVal(text)) => {
+                let words = collapse(text);
+                if words.is_empty() {
+                    //whitespace only, it separates whatever comes next
+                    self.tail = gap;
+                    self.force_space = space;
+                } else {
+                    self.put(&words, space);
+                    self.tail = trailing_whitespace(text).to_string();
+                }
+            }
+            Some(Token::Comment(text)) => self.put(&format!("#OBTW{}#TLDR", text), space),
+            //the end of a group always gets one space before it
+            Some(token @ Token::Mkay) | Some(token @ Token::Oic) => self.put(&keyword(token), true),
+            Some(token) => {
+                self.put(&keyword(token), space);
+                self.force_space = !matches!(token, Token::Hai | Token::Kthxbye);
+            }
+            None => self.put(&leaf.text, space),
+        }
+    }
+
+    fn put(&mut self, piece: &str, space: bool) {
+        if self.current.is_empty() {
+            self.current = INDENT.repeat(self.indent);
+            self.current.push_str(piece);
+        } else {
+            if space {
+                self.current.push(' ');
+            }
+            self.current.push_str(piece);
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            self.lines.push(std::mem::take(&mut self.current));
+            self.block_start = false;
+        }
+        self.force_space = false;
+    }
+
+    //all the whitespace in the source between the last piece and this leaf
+    fn take_gap(&mut self, leaf: &SyntaxToken) -> String {
+        let mut gap = std::mem::take(&mut self.tail);
+        gap.push_str(&leaf.leading);
+        if let Some(Token::Text(text)) | Some(Token::VarVal(text)) = &leaf.token {
+            gap.push_str(leading_whitespace(text));
+        }
+        gap
+    }
+
+    fn peek_gap(&self, element: &SyntaxElement) -> String {
+        let leaf = match element {
+            SyntaxElement::Token(leaf) => Some(leaf),
+            SyntaxElement::Node(node) => node.leaves().first().copied(),
+        };
+        let mut gap = self.tail.clone();
+        if let Some(leaf) = leaf {
+            gap.push_str(&leaf.leading);
+            if let Some(Token::Text(text)) = &leaf.token {
+                gap.push_str(leading_whitespace(text));
+            }
+        }
+        gap
+    }
+}
+
+//groups that are printed on a line of their own
+fn own_line(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Title | SyntaxKind::Item | SyntaxKind::Define | SyntaxKind::Audio | SyntaxKind::Video
+    )
+}
+
+/// how a token is written canonically
+fn keyword(token: &Token) -> String {
+    let text = match token {
+        Token::Hai => "#HAI",
+        Token::Kthxbye => "#KTHXBYE",
+        Token::Maek => "#MAEK",
+        Token::Oic => "#OIC",
+        Token::Gimmeh => "#GIMMEH",
+        Token::Mkay => "#MKAY",
+        Token::Head => "HEAD",
+        Token::Title => "TITLE",
+        Token::Paragraf => "PARAGRAF",
+        Token::Bold => "BOLD",
+        Token::Italics => "ITALICS",
+        Token::List => "LIST",
+        Token::Item => "ITEM",
+        Token::Newline => "NEWLINE",
+        Token::IHaz => "#I HAZ",
+        Token::ItIz => "#IT IZ",
+        Token::LemmeSee => "#LEMME SEE",
+        Token::I => "#I",
+        Token::Soundz(address) | Token::Vidz(address) => {
+            let name = if matches!(token, Token::Soundz(_)) { "SOUNDZ" } else { "VIDZ" };
+            return if address.is_empty() { name.to_string() } else { format!("{} {}", name, address) };
+        }
+        Token::VarDef(text) | Token::VarVal(text) | Token::VarUse(text) | Token::Text(text) => return collapse(text),
+        Token::Comment(text) => return format!("#OBTW{}#TLDR", text),
+    };
+    text.to_string()
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn leading_whitespace(text: &str) -> &str {
+    &text[..text.len() - text.trim_start().len()]
+}
+
+fn trailing_whitespace(text: &str) -> &str {
+    &text[text.trim_end().len()..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+    use crate::testing;
+
+    fn format_source(source: &str) -> String {
+        format(&Cst::parse(source))
+    }
+
+    #[test]
+    fn test_canonical_layout() {
+        let source = "#hai\n#maek head #gimmeh title Hi #mkay #oic\n\n\n#Maek Paragraf\n\t#gimmeh bold life#mkay. and \n\tmore  #GIMMEH NEWLINE  next\n#oic #kthxbye";
+        let expected = "#HAI\n#MAEK HEAD\n    #GIMMEH TITLE Hi #MKAY\n#OIC\n\n#MAEK PARAGRAF\n    #GIMMEH BOLD life #MKAY. and more #GIMMEH NEWLINE\n    next\n#OIC\n#KTHXBYE\n";
+        assert_eq!(format_source(source), expected);
+    }
+
+    #[test]
+    fn test_formatting_every_test_file_is_idempotent_and_keeps_the_output() {
+        for file in testing::test_files() {
+            let formatted = format(&file.cst);
+            assert_eq!(format_source(&formatted), formatted, "{}", file.path.display());
+            assert_eq!(html::render(&testing::parse(&formatted), true), html::render(&file.document, true), "{}", file.path.display());
+        }
+    }
+}
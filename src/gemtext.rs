@@ -0,0 +1,182 @@
+//gemtext generation for publishing on a gemini capsule. gemtext has a line for each kind of
+//thing and no inline markup: the title is a # heading, a paragraph is text lines, list items are
+//* lines and media players become => link lines. bold and italics become plain text, or with
+//--keep-emphasis keep ** and * around them
+use crate::ast::{self, Document, Node};
+
+/// renders a parsed document as a .gmi page
+pub fn render(document: &Document, keep_emphasis: bool) -> String {
+    let mut renderer = GemtextRenderer { blocks: Vec::new(), current: String::new(), keep_emphasis };
+    renderer.render_nodes(&document.prologue);
+    if let Some(head) = &document.head {
+        renderer.render_nodes(head);
+    }
+    renderer.render_nodes(&document.body);
+    renderer.end_paragraph();
+
+    let mut output = renderer.blocks.join("\n\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+struct GemtextRenderer {
+    blocks: Vec<String>, // finished blocks, separated by a blank line in the output
+    current: String,     // the paragraph being written, \n where #GIMMEH NEWLINE was
+    keep_emphasis: bool,
+}
+
+impl GemtextRenderer {
+    fn render_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.render_node(node);
+        }
+    }
+
+    fn render_node(&mut self, node: &Node) {
+        match node {
+            Node::Title(content) => {
+                let title = ast::plain_text(content).trim().to_string();
+                if !title.is_empty() {
+                    self.end_paragraph();
+                    self.blocks.push(format!("# {}", title));
+                }
+            }
+            Node::Paragraph(content) => {
+                self.end_paragraph();
+                self.render_nodes(content);
+                self.end_paragraph();
+            }
+            //gemtext lists are flat, nested items join the list around them
+            Node::List(_) | Node::Item(_) => {
+                self.end_paragraph();
+                let mut lines = Vec::new();
+                self.list(std::slice::from_ref(node), &mut lines);
+                if !lines.is_empty() {
+                    self.blocks.push(lines.join("\n"));
+                }
+            }
+            Node::Audio(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!("=> {} Audio", m.address.text));
+            }
+            Node::Video(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!("=> {} Video", m.address.text));
+            }
+            //a variable's markup is rendered again at every use
+            Node::Variable { value, .. } => self.render_nodes(value),
+            _ => inline(&mut self.current, std::slice::from_ref(node), self.keep_emphasis),
+        }
+    }
+
+    //a client wraps long lines itself, so each line of the paragraph is written whole
+    fn end_paragraph(&mut self) {
+        let paragraph = std::mem::take(&mut self.current);
+        let lines: Vec<String> = paragraph.split('\n')
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .map(|line| text_line(&line))
+            .collect();
+        if !lines.is_empty() {
+            self.blocks.push(lines.join("\n"));
+        }
+    }
+
+    fn list(&self, content: &[Node], lines: &mut Vec<String>) {
+        for node in content {
+            match node {
+                Node::List(items) => self.list(items, lines),
+                Node::Item(item) => {
+                    let mut text = String::new();
+                    inline(&mut text, item, self.keep_emphasis);
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    lines.push(format!("* {}", text).trim_end().to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+//the text of inline content, comments are dropped since gemtext has none
+fn inline(output: &mut String, nodes: &[Node], keep_emphasis: bool) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Bold(content) | Node::Italics(content) if keep_emphasis => {
+                let marker = if matches!(node, Node::Bold(_)) { "**" } else { "*" };
+                let mut inner = String::new();
+                inline(&mut inner, content, keep_emphasis);
+                //the markers go around the words, not the spaces next to them
+                let trimmed = inner.trim();
+                if trimmed.is_empty() {
+                    output.push_str(&inner);
+                } else {
+                    let start = inner.len() - inner.trim_start().len();
+                    output.push_str(&inner[..start]);
+                    output.push_str(&format!("{}{}{}", marker, trimmed, marker));
+                    output.push_str(&inner[start + trimmed.len()..]);
+                }
+            }
+            Node::Bold(content) | Node::Italics(content) => inline(output, content, keep_emphasis),
+            Node::Variable { value, .. } => inline(output, value, keep_emphasis),
+            Node::Newline => output.push('\n'),
+            _ => {}
+        }
+    }
+}
+
+//a text line that starts like a link, heading, list item, quote or preformatted toggle would be
+//read as one, gemtext has no escape so a space goes in front
+fn text_line(line: &str) -> String {
+    if ["=>", "#", "* ", ">", "```"].iter().any(|marker| line.starts_with(marker)) {
+        format!(" {}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_render() {
+        let document = testing::parse(testing::SAMPLE);
+        assert_eq!(render(&document, false), "# Test 8
+
+The meaning of life is happiness
+or so
+
+* fish and chips and mushy peas
+* tea
+
+=> theme.mp3 Audio
+
+=> https://vimeo.com/76979871 Video
+");
+        let emphasis = render(&document, true);
+        assert!(emphasis.contains("The meaning of *life* is **happiness**\n"), "{}", emphasis);
+        assert!(emphasis.contains("* fish and **chips** and mushy peas\n"), "{}", emphasis);
+    }
+
+    #[test]
+    fn test_text_lines_that_look_like_markup() {
+        let document = testing::parse("#HAI #MAEK HEAD #GIMMEH TITLE t #MKAY #OIC #MAEK PARAGRAF => not a link #GIMMEH NEWLINE > no quote #GIMMEH NEWLINE *not* a list #OIC #KTHXBYE");
+        assert_eq!(render(&document, false), "# t\n\n => not a link\n > no quote\n*not* a list\n");
+    }
+
+    //every player becomes a link line and nothing else does
+    #[test]
+    fn test_every_test_file_links_only_its_media() {
+        for file in testing::test_files() {
+            let media = file.source.matches("#GIMMEH VIDZ").count() + file.source.matches("#GIMMEH SOUNDZ").count();
+            let gemtext = render(&file.document, false);
+            assert_eq!(gemtext.lines().filter(|line| line.starts_with("=> ")).count(), media, "{}", file.path.display());
+            assert_eq!(gemtext.lines().filter(|line| line.starts_with("# ")).count(), 1, "{}", file.path.display());
+        }
+    }
+}
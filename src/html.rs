@@ -0,0 +1,232 @@
+//html generation from the document tree, plus escaping for the places a value can end up
+use crate::ast::{self, Document, Node};
+use crate::media;
+
+/// renders a parsed document as a complete html page
+pub fn render(document: &Document, keep_comments: bool) -> String {
+    let mut renderer = HtmlRenderer { output: String::new(), keep_comments };
+    renderer.render_document(document);
+    renderer.output
+}
+
+struct HtmlRenderer {
+    output: String,
+    keep_comments: bool,
+}
+
+impl HtmlRenderer {
+    fn render_document(&mut self, document: &Document) {
+        self.output.push_str("<!DOCTYPE html>\n<html>\n");
+        self.render_nodes(&document.prologue);
+        if let Some(head) = &document.head {
+            self.output.push_str("<head>\n");
+            self.render_nodes(head);
+            self.output.push_str("</head>\n");
+        }
+        self.output.push_str("<body>\n");
+        self.render_nodes(&document.body);
+        self.start_line();
+        self.output.push_str("</body>\n");
+        self.output.push_str("</html>\n");
+    }
+
+    //block tags go on their own line, after text at the top of the body
+    fn start_line(&mut self) {
+        if !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    fn render_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.render_node(node);
+        }
+    }
+
+    fn render_node(&mut self, node: &Node) {
+        match node {
+            //<title> only holds text, so markup and comments inside it are dropped
+            Node::Title(content) => {
+                self.output.push_str("<title>");
+                self.output.push_str(&escape_text(&ast::plain_text(content)));
+                self.output.push_str("</title>\n");
+            }
+            Node::Paragraph(content) => {
+                self.start_line();
+                self.output.push_str("<p>");
+                self.render_nodes(content);
+                self.output.push_str("</p>\n");
+            }
+            Node::List(content) => {
+                self.start_line();
+                self.output.push_str("<ul>\n");
+                self.render_nodes(content);
+                self.output.push_str("</ul>\n");
+            }
+            Node::Item(content) => {
+                self.output.push_str("<li>");
+                self.render_nodes(content);
+                self.output.push_str("</li>\n");
+            }
+            Node::Bold(content) => {
+                self.output.push_str("<b>");
+                self.render_nodes(content);
+                self.output.push_str("</b>");
+            }
+            Node::Italics(content) => {
+                self.output.push_str("<i>");
+                self.render_nodes(content);
+                self.output.push_str("</i>");
+            }
+            Node::Text(text) => self.output.push_str(&escape_text(text)),
+            Node::Newline => self.output.push_str("<br>\n"),
+            Node::Audio(m) => self.output.push_str(&media::render_audio(&m.address, &m.options)),
+            Node::Video(m) => self.output.push_str(&media::render_video(&m.address, &m.options)),
+            //a variable's markup is rendered again at every use
+            Node::Variable { value, .. } => self.render_nodes(value),
+            Node::Define { .. } => {}
+            Node::Comment(text) => {
+                if self.keep_comments {
+                    //comments between blocks get their own line
+                    let own_line = self.output.ends_with('\n');
+                    self.output.push_str(&html_comment(text));
+                    if own_line {
+                        self.output.push('\n');
+                    }
+                }
+            }
+        }
+    }
+}
+
+///wraps text in an html comment, "--" may not appear inside one so it is split up
+pub fn html_comment(text: &str) -> String {
+    let mut text = text.trim().to_string();
+    while text.contains("--") {
+        text = text.replace("--", "- -");
+    }
+    format!("<!-- {} -->", text)
+}
+
+/// text between tags, e.g. inside <p> or <title>
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// text or an attribute value read back from html: the entities escape_text and
+/// escape_attribute write, &nbsp; and numeric references are decoded, anything else is kept
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                name => {
+                    let number = name.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, length)) => {
+                unescaped.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// a double quoted attribute value, e.g. src="..."
+pub fn escape_attribute(text: &str) -> String {
+    escape_text(text).replace('"', "&quot;")
+}
+
+/// a variable value spliced into an address, characters that are not allowed in a url are
+/// percent-encoded while ones with a meaning there (: / ? & and existing %XX) are kept
+pub fn encode_url(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=%".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_escape_text_and_attribute() {
+        assert_eq!(escape_text("Fish & <Chips>"), "Fish &amp; &lt;Chips&gt;");
+        assert_eq!(escape_attribute("a.mp3?x=1&y=\"2\""), "a.mp3?x=1&amp;y=&quot;2&quot;");
+        assert_eq!(unescape("Fish &amp; &lt;Chips&gt; &#39;&#x41;&quot; & &copy;"), "Fish & <Chips> 'A\" & &copy;");
+    }
+
+    #[test]
+    fn test_encode_url() {
+        assert_eq!(encode_url("https://cdn.example.com/My Clips"), "https://cdn.example.com/My%20Clips");
+        assert_eq!(encode_url("caf\u{e9}\"<>"), "caf%C3%A9%22%3C%3E");
+        assert_eq!(encode_url("The%20Simpsons"), "The%20Simpsons");
+    }
+
+    #[test]
+    fn test_variable_markup_is_rendered_at_each_use() {
+        let value = vec![Node::Bold(vec![Node::Text("Homer ".to_string())]), Node::Text("Simpson ".to_string())];
+        let use_site = Node::Variable { name: "name".to_string(), value: value.clone() };
+        let document = Document {
+            prologue: vec![Node::Define { name: "name".to_string(), value }],
+            head: Some(vec![Node::Title(vec![use_site.clone()])]),
+            body: vec![Node::Paragraph(vec![use_site.clone(), Node::Italics(vec![use_site])])],
+            trailing: Vec::new(),
+        };
+
+        let html = render(&document, false);
+        assert!(html.contains("<title>Homer Simpson </title>"));
+        assert!(html.contains("<p><b>Homer </b>Simpson <i><b>Homer </b>Simpson </i></p>"));
+    }
+
+    #[test]
+    fn test_start_time_joins_address_fragment() {
+        let document = testing::parse("#HAI #GIMMEH SOUNDZ theme.mp3#x start=5 #MKAY #KTHXBYE");
+        assert!(render(&document, false).contains("<source src=\"theme.mp3#x&amp;t=5\" type=\"audio/mpeg\">"));
+    }
+
+    //tests/TestN.html is the expected output for tests/TestN.lol, comments left out
+    #[test]
+    fn test_golden_files() {
+        testing::check_golden_files("html", |document| render(document, false));
+    }
+}
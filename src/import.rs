@@ -0,0 +1,807 @@
+//markdown (commonmark) to lolcode, for moving existing .md files over. the common subset maps
+//one to one: a leading # heading is the title, paragraphs, **bold**, *italics*, lists, hard
+//breaks, comments and media (images of audio/video files and <audio>/<video>/<iframe> html).
+//everything else is carried over as well as it can be and reported
+use crate::address::Address;
+use crate::convert::SourceWriter;
+use crate::cst::Cst;
+use crate::fmt;
+use crate::html;
+use crate::media;
+
+/// the .lol source for a markdown file, and a warning for each construct that could not be
+/// carried over exactly. name is the title when the file does not start with one
+pub fn markdown_to_lol(markdown: &str, name: &str) -> (String, Vec<String>) {
+    let mut importer = Importer { output: SourceWriter::new(), warnings: Vec::new() };
+    let blocks = read_blocks(markdown, &mut importer.warnings);
+
+    //the title has to come first, only comments may be written before it
+    let title = blocks.iter().position(|block| !matches!(block, Block::Comment(_)))
+        .filter(|&i| matches!(blocks[i], Block::Heading { level: 1, .. }));
+    //lolcode needs a head before the first paragraph
+    if title.is_none() {
+        importer.warn(1, &format!("A LOLCODE document starts with a title; there was no heading at the top, so '{}' was used.", name));
+        importer.title(&[Inline::Text(name.to_string())], 1);
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        if Some(i) == title {
+            if let Block::Heading { text, line, .. } = block {
+                importer.title(&read_inline(text), *line);
+            }
+        } else {
+            importer.block(block);
+        }
+    }
+    importer.output.push_str("#KTHXBYE\n");
+    (fmt::format(&Cst::parse(&importer.output.finish())), importer.warnings)
+}
+
+//a markdown block with the line it starts on
+enum Block {
+    Heading { level: usize, text: String, line: usize },
+    Paragraph { text: String, line: usize },
+    List { ordered: bool, items: Vec<(String, usize)>, line: usize },
+    Code { text: String, line: usize },
+    Quote { text: String, line: usize },
+    Html { text: String, line: usize },
+    Comment(String),
+    Rule { line: usize },
+}
+
+//where inline content goes, lolcode allows less inside a span, an item or the title
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Paragraph,
+    Item,
+    Span,
+    Title,
+}
+
+//the parts of a paragraph
+#[derive(Debug, Clone, PartialEq)]
+enum Inline {
+    Text(String),
+    Bold(Vec<Inline>),
+    Italics(Vec<Inline>),
+    Break,
+    Comment(String),
+    Code(String),
+    Link { text: Vec<Inline>, url: String },
+    Image { alt: String, src: String },
+    Html(String),
+}
+
+struct Importer {
+    output: SourceWriter,
+    warnings: Vec<String>,
+}
+
+impl Importer {
+    fn warn(&mut self, line: usize, message: &str) {
+        self.warnings.push(format!("Warning: line {}: {}", line, message));
+    }
+
+    fn title(&mut self, inlines: &[Inline], line: usize) {
+        self.output.push_str("#MAEK HEAD #GIMMEH TITLE ");
+        self.inline(inlines, Context::Title, line);
+        self.output.push_str(" #MKAY #OIC\n");
+    }
+
+    fn block(&mut self, block: &Block) {
+        match block {
+            Block::Heading { text, line, .. } => {
+                self.warn(*line, "Only one heading, the title at the top, can be written in LOLCODE; this one became a bold paragraph.");
+                let inlines = vec![Inline::Bold(read_inline(text))];
+                self.paragraph(&inlines, *line);
+            }
+            Block::Paragraph { text, line } => {
+                let inlines = read_inline(text);
+                //a paragraph of only a media player is written on its own, like the player
+                if let [Inline::Image { src, .. }] = inlines.as_slice()
+                    && media_kind(src).is_some()
+                {
+                    self.inline(&inlines, Context::Paragraph, *line);
+                    self.output.push('\n');
+                    return;
+                }
+                self.paragraph(&inlines, *line);
+            }
+            Block::List { ordered, items, line } => {
+                if *ordered {
+                    self.warn(*line, "Numbered lists cannot be written in LOLCODE; this one became a bulleted list.");
+                }
+                self.output.push_str("#MAEK LIST\n");
+                for (item, line) in items {
+                    self.output.push_str("#GIMMEH ITEM ");
+                    self.inline(&read_inline(item), Context::Item, *line);
+                    self.output.push_str(" #MKAY\n");
+                }
+                self.output.push_str("#OIC\n");
+            }
+            Block::Code { text, line } => {
+                self.warn(*line, "Code blocks cannot be written in LOLCODE; the code became a paragraph with a line break per line.");
+                let mut inlines = Vec::new();
+                for (i, code_line) in text.lines().enumerate() {
+                    if i > 0 {
+                        inlines.push(Inline::Break);
+                    }
+                    inlines.push(Inline::Text(code_line.to_string()));
+                }
+                self.paragraph(&inlines, *line);
+            }
+            Block::Quote { text, line } => {
+                self.warn(*line, "Block quotes cannot be written in LOLCODE; the quote became a paragraph.");
+                self.paragraph(&read_inline(text), *line);
+            }
+            Block::Html { text, line } => match html_media(text) {
+                Some(media) => {
+                    self.output.push_str(&media);
+                    self.output.push('\n');
+                }
+                None => self.warn(*line, "HTML blocks cannot be written in LOLCODE; this one was left out."),
+            },
+            Block::Comment(text) => {
+                self.output.push_str(&comment(text));
+                self.output.push('\n');
+            }
+            Block::Rule { line } => self.warn(*line, "Horizontal rules cannot be written in LOLCODE; this one was left out."),
+        }
+    }
+
+    fn paragraph(&mut self, inlines: &[Inline], line: usize) {
+        self.output.push_str("#MAEK PARAGRAF ");
+        self.inline(inlines, Context::Paragraph, line);
+        self.output.push_str(" #OIC\n");
+    }
+
+    fn inline(&mut self, inlines: &[Inline], context: Context, line: usize) {
+        for inline in inlines {
+            match inline {
+                Inline::Text(text) => self.text(text, line),
+                Inline::Bold(content) | Inline::Italics(content) if context == Context::Title => self.inline(content, context, line),
+                Inline::Bold(content) | Inline::Italics(content) if context == Context::Span => {
+                    self.warn(line, "Bold and italics cannot be nested in LOLCODE; the inner one was left out.");
+                    self.inline(content, context, line);
+                }
+                Inline::Bold(content) | Inline::Italics(content) => {
+                    let keyword = if matches!(inline, Inline::Bold(_)) { "BOLD" } else { "ITALICS" };
+                    self.output.push_str(&format!("#GIMMEH {} ", keyword));
+                    self.inline(content, Context::Span, line);
+                    self.output.push_str(" #MKAY");
+                }
+                Inline::Break if context == Context::Paragraph => self.output.push_str(" #GIMMEH NEWLINE "),
+                Inline::Break => {
+                    self.warn(line, "Line breaks can only be written in a paragraph in LOLCODE; this one became a space.");
+                    self.output.push(' ');
+                }
+                Inline::Comment(text) => self.output.push_str(&comment(text)),
+                Inline::Code(code) => {
+                    self.warn(line, "Inline code cannot be written in LOLCODE; it became plain text.");
+                    self.text(code, line);
+                }
+                Inline::Link { text, url } => {
+                    self.warn(line, "Links cannot be written in LOLCODE; the link text is followed by its address in parentheses.");
+                    self.inline(text, context, line);
+                    self.text(&format!(" ({})", url), line);
+                }
+                Inline::Image { alt, src } => match media_kind(src) {
+                    Some(kind) if context == Context::Paragraph => self.media(kind, src, "", line),
+                    _ => {
+                        self.warn(line, "Only audio and video files can be embedded in LOLCODE, and only in a paragraph; the image became its description.");
+                        self.text(alt, line);
+                    }
+                },
+                Inline::Html(tag) => self.warn(line, &format!("Inline HTML cannot be written in LOLCODE; '{}' was left out.", tag)),
+            }
+        }
+    }
+
+    //# always starts a keyword in lolcode, there is no way to write it in text
+    fn text(&mut self, text: &str, line: usize) {
+        if text.contains('#') {
+            self.warn(line, "'#' cannot be written in LOLCODE text; it was left out.");
+        }
+        self.output.push_text(&text.replace('#', ""));
+    }
+
+    fn media(&mut self, kind: &str, src: &str, options: &str, line: usize) {
+        match Address::parse(src, &mut Vec::new()) {
+            Ok(_) => self.output.push_str(&format!(" #GIMMEH {} {}{} #MKAY ", kind, src, options)),
+            Err(e) => self.warn(line, &format!("{} The media was left out.", e)),
+        }
+    }
+}
+
+fn comment(text: &str) -> String {
+    format!("#OBTW {} #TLDR", text.trim().replace('#', ""))
+}
+
+//SOUNDZ or VIDZ for an address a player can show
+fn media_kind(src: &str) -> Option<&'static str> {
+    if media::audio_mime_type(src).is_some() {
+        return Some("SOUNDZ");
+    }
+    let address = Address::parse(src, &mut Vec::new()).ok()?;
+    if media::video_mime_type(src).is_some() || media::video_provider(&address).is_some() {
+        return Some("VIDZ");
+    }
+    None
+}
+
+//an <audio>, <video> or <iframe> player as #GIMMEH SOUNDZ/VIDZ with the options it was written with
+fn html_media(html: &str) -> Option<String> {
+    let lower = html.to_lowercase();
+    let (kind, tag_start) = ["audio", "video", "iframe"].iter()
+        .filter_map(|tag| lower.find(&format!("<{}", tag)).map(|i| (*tag, i)))
+        .min_by_key(|(_, i)| *i)?;
+    let tag = &html[tag_start..tag_start + html[tag_start..].find('>')?];
+
+    let mut options = Vec::new();
+    let src = if kind == "iframe" {
+        attribute(tag, "src")?
+    } else {
+        for flag in ["autoplay", "loop", "muted"] {
+            if tag.split_whitespace().any(|word| word == flag) {
+                options.push(flag.to_string());
+            }
+        }
+        let source = html.find("<source").map(|i| &html[i..]);
+        let src = source.and_then(|s| attribute(s, "src")).or_else(|| attribute(tag, "src"))?;
+        //the start time is written as a media fragment on the address
+        match src.rsplit_once("#t=") {
+            Some((address, start)) if start.parse::<u32>().is_ok() => {
+                options.push(format!("start={}", start));
+                address.to_string()
+            }
+            _ => src,
+        }
+    };
+    for dimension in ["width", "height"] {
+        if let Some(value) = attribute(tag, dimension) {
+            options.push(format!("{}={}", dimension, value));
+        }
+    }
+    if let Some(track) = html.find("<track").and_then(|i| attribute(&html[i..], "src")) {
+        options.push(format!("captions={}", track));
+    }
+    Address::parse(&src, &mut Vec::new()).ok()?;
+    let keyword = if kind == "audio" { "SOUNDZ" } else { "VIDZ" };
+    let options: String = options.iter().map(|option| format!(" {}", option)).collect();
+    Some(format!("#GIMMEH {} {}{} #MKAY", keyword, src, options))
+}
+
+//the value of name="..." in the first tag of html
+fn attribute(html: &str, name: &str) -> Option<String> {
+    let tag = &html[..html.find('>').unwrap_or(html.len())];
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + tag[start..].find('"')?;
+    Some(html::unescape(&tag[start..end]))
+}
+
+
+//---- blocks ----
+
+fn read_blocks(markdown: &str, warnings: &mut Vec<String>) -> Vec<Block> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        let number = i + 1;
+        let indent = line.len() - line.trim_start().len();
+        if trimmed.is_empty() {
+            i += 1;
+        } else if indent >= 4 || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let (text, next) = code_block(&lines, i);
+            blocks.push(Block::Code { text, line: number });
+            i = next;
+        } else if trimmed.starts_with("<!--") {
+            //a comment runs to the line with -->
+            let mut text = String::new();
+            while i < lines.len() {
+                text.push_str(lines[i]);
+                text.push('\n');
+                i += 1;
+                if lines[i - 1].contains("-->") {
+                    break;
+                }
+            }
+            let text = text.trim();
+            let inner = text.trim_start_matches("<!--");
+            let inner = inner.split("-->").next().unwrap_or(inner);
+            blocks.push(Block::Comment(inner.trim().to_string()));
+        } else if trimmed.starts_with('<') && trimmed[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') {
+            let (text, next) = until_blank(&lines, i, |line| line.to_string());
+            blocks.push(Block::Html { text, line: number });
+            i = next;
+        } else if let Some((level, text)) = atx_heading(trimmed) {
+            blocks.push(Block::Heading { level, text, line: number });
+            i += 1;
+        } else if is_rule(trimmed) {
+            blocks.push(Block::Rule { line: number });
+            i += 1;
+        } else if trimmed.starts_with('>') {
+            let (text, next) = until_blank(&lines, i, |line| {
+                let line = line.trim_start();
+                line.strip_prefix('>').unwrap_or(line).trim().to_string()
+            });
+            blocks.push(Block::Quote { text, line: number });
+            i = next;
+        } else if let Some((ordered, _)) = list_marker(line) {
+            let (items, next) = list(&lines, i, warnings);
+            blocks.push(Block::List { ordered, items, line: number });
+            i = next;
+        } else {
+            //a paragraph ends at a blank line or where another block starts
+            let mut text = String::new();
+            while i < lines.len() {
+                let line = lines[i];
+                let trimmed = line.trim_start();
+                if !text.is_empty()
+                    && (trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('>') || trimmed.starts_with("```")
+                        || (trimmed.starts_with('<') && !trimmed.starts_with("<br")) || list_marker(line).is_some() || is_rule(trimmed.trim()))
+                {
+                    break;
+                }
+                if !text.is_empty() && setext_level(trimmed.trim()).is_some() {
+                    break;
+                }
+                text.push_str(trimmed);
+                text.push('\n');
+                i += 1;
+            }
+            //an underline of = or - makes the paragraph a heading
+            match lines.get(i).and_then(|line| setext_level(line.trim())) {
+                Some(level) => {
+                    blocks.push(Block::Heading { level, text: text.trim().to_string(), line: number });
+                    i += 1;
+                }
+                None => blocks.push(Block::Paragraph { text: text.trim_end_matches('\n').to_string(), line: number }),
+            }
+        }
+    }
+    blocks
+}
+
+//the lines from start up to the next blank one, each passed through clean
+fn until_blank(lines: &[&str], start: usize, clean: impl Fn(&str) -> String) -> (String, usize) {
+    let mut text = Vec::new();
+    let mut i = start;
+    while i < lines.len() && !lines[i].trim().is_empty() {
+        text.push(clean(lines[i]));
+        i += 1;
+    }
+    (text.join("\n"), i)
+}
+
+//a fenced block up to its closing fence, or indented lines
+fn code_block(lines: &[&str], start: usize) -> (String, usize) {
+    let first = lines[start].trim();
+    let mut code = Vec::new();
+    let mut i = start + 1;
+    if first.starts_with("```") || first.starts_with("~~~") {
+        let fence = &first[..3];
+        while i < lines.len() && !lines[i].trim().starts_with(fence) {
+            code.push(lines[i]);
+            i += 1;
+        }
+        return (code.join("\n"), i + 1);
+    }
+    code.push(strip_indent(lines[start]));
+    while i < lines.len() && (lines[i].starts_with("    ") || lines[i].trim().is_empty()) {
+        code.push(strip_indent(lines[i]));
+        i += 1;
+    }
+    (code.join("\n").trim_end().to_string(), i)
+}
+
+//the line without the four whitespace characters that make it code, by character since the
+//indent may be wider than a byte
+fn strip_indent(line: &str) -> &str {
+    let mut rest = line;
+    for _ in 0..4 {
+        match rest.chars().next() {
+            Some(c) if c.is_whitespace() => rest = &rest[c.len_utf8()..],
+            _ => break,
+        }
+    }
+    rest
+}
+
+fn atx_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    //a closing run of # is not part of the text
+    let text = rest.trim();
+    let closed = text.trim_end_matches('#');
+    let text = if closed.is_empty() || closed.ends_with(' ') { closed.trim_end() } else { text };
+    Some((level, text.to_string()))
+}
+
+fn setext_level(line: &str) -> Option<usize> {
+    if !line.is_empty() && line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if line.len() >= 2 && line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+//three or more *, - or _ and nothing else
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ["*", "-", "_"].iter().any(|mark| marks.chars().all(|c| c.to_string() == *mark))
+}
+
+//("- ", "1. " ...) whether the list is numbered and where the item text starts
+fn list_marker(line: &str) -> Option<(bool, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (ordered, marker) = if rest.starts_with(['-', '*', '+']) {
+        (false, 1)
+    } else if (1..=9).contains(&digits) && rest[digits..].starts_with(['.', ')']) {
+        (true, digits + 1)
+    } else {
+        return None;
+    };
+    let after = &rest[marker..];
+    if !(after.is_empty() || after.starts_with([' ', '\t'])) || is_rule(rest.trim()) {
+        return None;
+    }
+    Some((ordered, indent + marker + after.len() - after.trim_start().len()))
+}
+
+//the items of a list, lines that are not a new item continue the one before
+fn list(lines: &[&str], start: usize, warnings: &mut Vec<String>) -> (Vec<(String, usize)>, usize) {
+    let base = lines[start].len() - lines[start].trim_start().len();
+    let mut items: Vec<(String, usize)> = Vec::new();
+    let mut i = start;
+    while i < lines.len() {
+        let line = lines[i];
+        let indent = line.len() - line.trim_start().len();
+        if line.trim().is_empty() {
+            //a blank line ends the list unless the list goes on after it
+            match lines.get(i + 1) {
+                Some(next) if list_marker(next).is_some() || next.starts_with("  ") => {
+                    i += 1;
+                    continue;
+                }
+                _ => break,
+            }
+        }
+        match list_marker(line) {
+            Some((_, text_start)) => {
+                if indent > base && !items.is_empty() {
+                    warnings.push(format!("Warning: line {}: Nested lists cannot be written in LOLCODE; the inner items joined the outer list.", i + 1));
+                }
+                items.push((line[text_start..].trim().to_string(), i + 1));
+            }
+            None if items.is_empty() || indent == 0 && is_block_start(line) => break,
+            None => {
+                let (text, _) = items.last_mut().unwrap_or_else(|| unreachable!());
+                text.push('\n');
+                text.push_str(line.trim());
+            }
+        }
+        i += 1;
+    }
+    (items, i)
+}
+
+fn is_block_start(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with(['#', '>', '<']) || trimmed.starts_with("```") || is_rule(trimmed)
+}
+
+//---- inline ----
+
+fn read_inline(text: &str) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut inlines = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..].iter().take(5).collect();
+        match c {
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                push_break(&mut inlines, &mut text);
+                i += 2;
+                continue;
+            }
+            '\\' if chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                text.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            //two spaces before the end of a line are a hard break, otherwise the line ends in a space
+            '\n' => {
+                if text.ends_with("  ") {
+                    push_break(&mut inlines, &mut text);
+                } else {
+                    text.truncate(text.trim_end_matches(' ').len());
+                    text.push(' ');
+                }
+                i += 1;
+                continue;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|c| **c == '`').count();
+                let fence: String = "`".repeat(run);
+                if let Some(end) = find(&chars, i + run, &fence) {
+                    flush(&mut inlines, &mut text);
+                    let code: String = chars[i + run..end].iter().collect();
+                    inlines.push(Inline::Code(code.trim().to_string()));
+                    i = end + run;
+                    continue;
+                }
+            }
+            '*' | '_' => {
+                if let Some((inline, next)) = emphasis(&chars, i) {
+                    flush(&mut inlines, &mut text);
+                    inlines.push(inline);
+                    i = next;
+                    continue;
+                }
+                //a run that opens nothing is text, all of it
+                let run = chars[i..].iter().take_while(|x| **x == c).count();
+                text.extend(&chars[i..i + run]);
+                i += run;
+                continue;
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => {
+                if let Some((alt, src, next)) = link(&chars, i + 1) {
+                    flush(&mut inlines, &mut text);
+                    inlines.push(Inline::Image { alt, src });
+                    i = next;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((label, url, next)) = link(&chars, i) {
+                    flush(&mut inlines, &mut text);
+                    inlines.push(Inline::Link { text: read_inline(&label), url });
+                    i = next;
+                    continue;
+                }
+            }
+            '<' if rest.starts_with("<!--") => {
+                if let Some(end) = find(&chars, i + 4, "-->") {
+                    flush(&mut inlines, &mut text);
+                    inlines.push(Inline::Comment(chars[i + 4..end].iter().collect::<String>().trim().to_string()));
+                    i = end + 3;
+                    continue;
+                }
+            }
+            '<' => {
+                if let Some(end) = find(&chars, i + 1, ">") {
+                    let tag: String = chars[i + 1..end].iter().collect();
+                    if tag.contains("://") && !tag.contains(' ') {
+                        flush(&mut inlines, &mut text);
+                        inlines.push(Inline::Link { text: vec![Inline::Text(tag.clone())], url: tag });
+                        i = end + 1;
+                        continue;
+                    }
+                    if tag.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') {
+                        let name: String = tag.trim_start_matches('/').chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+                        if name.eq_ignore_ascii_case("br") {
+                            push_break(&mut inlines, &mut text);
+                        } else {
+                            flush(&mut inlines, &mut text);
+                            inlines.push(Inline::Html(format!("<{}>", tag)));
+                        }
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+            '&' => {
+                if let Some(end) = find(&chars, i, ";").filter(|end| end - i <= 6) {
+                    let entity: String = chars[i..=end].iter().collect();
+                    let decoded = html::unescape(&entity);
+                    if decoded != entity {
+                        text.push_str(&decoded);
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        text.push(c);
+        i += 1;
+    }
+    flush(&mut inlines, &mut text);
+    inlines
+}
+
+fn flush(inlines: &mut Vec<Inline>, text: &mut String) {
+    if !text.is_empty() {
+        inlines.push(Inline::Text(std::mem::take(text)));
+    }
+}
+
+fn push_break(inlines: &mut Vec<Inline>, text: &mut String) {
+    text.truncate(text.trim_end().len());
+    flush(inlines, text);
+    inlines.push(Inline::Break);
+}
+
+//where pattern next appears in chars, from start on
+fn find(chars: &[char], start: usize, pattern: &str) -> Option<usize> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    (start..chars.len()).find(|&i| chars[i..].starts_with(&pattern))
+}
+
+//*x*, _x_, **x**, __x__ and ***x***. the closing run must have the same length and follow a
+//non-space, an _ run may not start or end inside a word
+fn emphasis(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    let c = chars[start];
+    let run = chars[start..].iter().take_while(|x| **x == c).count();
+    let after = *chars.get(start + run)?;
+    if after.is_whitespace() || (c == '_' && start > 0 && chars[start - 1].is_alphanumeric()) {
+        return None;
+    }
+    let mut i = start + run;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        let close = chars[i..].iter().take_while(|x| **x == c).count();
+        if close == 0 {
+            i += 1;
+            continue;
+        }
+        let closes = close == run
+            && !chars[i - 1].is_whitespace()
+            && !(c == '_' && chars.get(i + close).is_some_and(|x| x.is_alphanumeric()));
+        if closes {
+            let inner: String = chars[start + run..i].iter().collect();
+            let content = read_inline(&inner);
+            let inline = match run {
+                1 => Inline::Italics(content),
+                2 => Inline::Bold(content),
+                _ => Inline::Bold(vec![Inline::Italics(content)]),
+            };
+            return Some((inline, i + close));
+        }
+        i += close;
+    }
+    None
+}
+
+//[label](url) starting at the [, with the position after the )
+fn link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut i = start;
+    let close = loop {
+        match chars.get(i)? {
+            '\\' => i += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, ")")?;
+    let label: String = chars[start + 1..close].iter().collect();
+    let target: String = chars[close + 2..end].iter().collect();
+    //a "title" after the address is dropped
+    let url = target.split_whitespace().next().unwrap_or("").trim_start_matches('<').trim_end_matches('>').to_string();
+    Some((label, url, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown;
+    use crate::testing;
+
+    fn compile(source: &str) -> String {
+        html::render(&testing::parse(source), true)
+    }
+
+    #[test]
+    fn test_import_through_the_html_backend() {
+        let markdown = "# Fish & Chips
+
+*What is the meaning of life.* The meaning of **life** is happiness.\\
+Really
+truly.
+
+- one
+- **two**
+  continued
+
+<!-- a note -->
+
+![theme](theme.mp3)
+
+<div>
+<video controls autoplay width=\"640\"><source src=\"intro.mp4#t=30\" type=\"video/mp4\"></video>
+</div>
+";
+        let (lol, warnings) = markdown_to_lol(markdown, "fish");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let html = compile(&lol);
+        assert!(html.contains("<title>Fish &amp; Chips</title>"), "{}", html);
+        assert!(html.contains("<p><i>What is the meaning of life.</i> The meaning of <b>life</b> is happiness.<br>\nReally truly.</p>\n"), "{}", html);
+        assert!(html.contains("<ul>\n<li>one</li>\n<li><b>two</b> continued</li>\n</ul>\n<!-- a note -->\n"), "{}", html);
+        assert!(html.contains("<audio controls><source src=\"theme.mp3\" type=\"audio/mpeg\"></audio>"), "{}", html);
+        assert!(html.contains("<video controls autoplay width=\"640\"><source src=\"intro.mp4#t=30\" type=\"video/mp4\"></video>"), "{}", html);
+    }
+
+    #[test]
+    fn test_unsupported_constructs_are_reported() {
+        let markdown = "Intro\n\n## Section\n\n1. first\n2. [second](https://example.com)\n\n> quoted `code`\n\n---\n\nIssue #42 ![cat](cat.png)\n";
+        let (lol, warnings) = markdown_to_lol(markdown, "notes");
+        compile(&lol);
+        let expected = [
+            "line 1: A LOLCODE document starts with a title",
+            "line 3: Only one heading",
+            "line 5: Numbered lists",
+            "line 6: Links",
+            "line 8: Block quotes",
+            "line 8: Inline code",
+            "line 10: Horizontal rules",
+            "line 12: '#'",
+            "line 12: Only audio and video",
+        ];
+        assert_eq!(warnings.len(), expected.len(), "{:?}", warnings);
+        for (warning, expected) in warnings.iter().zip(expected) {
+            assert!(warning.starts_with(&format!("Warning: {}", expected)), "{}", warning);
+        }
+        assert!(lol.contains("second (https://example.com)"));
+        assert!(lol.contains("#GIMMEH TITLE notes #MKAY"));
+    }
+
+    #[test]
+    fn test_code_indented_with_wide_spaces() {
+        let (lol, warnings) = markdown_to_lol("# Code\n\n\u{3000}\u{3000}let x = 1;\n", "code");
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(compile(&lol).contains("<p>let x = 1;</p>"), "{}", lol);
+    }
+
+    //text starting with a word the lexer reads as a keyword still compiles
+    #[test]
+    fn test_keyword_words_in_text() {
+        let (lol, warnings) = markdown_to_lol("# Lists\n\nList of things **head** and\nparagraf.\n\n- HEAD first\n- list\n", "lists");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let html = compile(&lol);
+        assert!(html.contains("<p>List of things <b>head</b> and paragraf.</p>"), "{}\n{}", lol, html);
+        assert!(html.contains("<li>HEAD first</li>\n<li>list</li>"), "{}\n{}", lol, html);
+    }
+
+    //every test file exported with the markdown backend comes back as the same document
+    #[test]
+    fn test_round_trip_every_test_file() {
+        for file in testing::test_files() {
+            //markdown has no text outside a paragraph, so the first import may wrap some in one.
+            //after that markdown and html both stay the same
+            let markdown = markdown::render(&file.document, true);
+            let (lol, warnings) = markdown_to_lol(&markdown, "test");
+            assert!(warnings.is_empty(), "{}: {:?}", file.path.display(), warnings);
+            let imported = testing::parse(&lol);
+            assert_eq!(markdown::render(&imported, true), markdown, "{}", file.path.display());
+
+            let (again, _) = markdown_to_lol(&markdown::render(&imported, true), "test");
+            assert_eq!(again, lol, "{}", file.path.display());
+            assert_eq!(compile(&again), html::render(&imported, true), "{}", file.path.display());
+        }
+    }
+}
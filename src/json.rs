@@ -0,0 +1,373 @@
+//a small json reader and writer, enough for the ast export and import. objects keep their keys
+//in the order they were written so the output is stable
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// the value of a key in an object
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// a whole, non-negative number
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= u64::MAX as f64 => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    /// the value written out with two spaces of indentation per level
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::new();
+        self.write_pretty(&mut output, 0);
+        output
+    }
+
+    fn write_pretty(&self, output: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth + 1);
+        match self {
+            Value::Null => output.push_str("null"),
+            Value::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+            //whole numbers are written without a fraction
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                let _ = write!(output, "{}", *n as i64);
+            }
+            //json has no infinity or nan
+            Value::Number(n) if !n.is_finite() => output.push_str("null"),
+            Value::Number(n) => {
+                let _ = write!(output, "{}", n);
+            }
+            Value::String(s) => output.push_str(&quote(s)),
+            Value::Array(items) if items.is_empty() => output.push_str("[]"),
+            Value::Array(items) => {
+                output.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    output.push_str(&indent);
+                    item.write_pretty(output, depth + 1);
+                    output.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                output.push_str(&"  ".repeat(depth));
+                output.push(']');
+            }
+            Value::Object(fields) if fields.is_empty() => output.push_str("{}"),
+            Value::Object(fields) => {
+                output.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    output.push_str(&indent);
+                    output.push_str(&quote(key));
+                    output.push_str(": ");
+                    value.write_pretty(output, depth + 1);
+                    output.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                output.push_str(&"  ".repeat(depth));
+                output.push('}');
+            }
+        }
+    }
+}
+
+/// a json string literal, with quotes
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// reads a json document, the error says where it went wrong
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut reader = Reader { chars: text.chars().collect(), pos: 0, line: 1, depth: 0 };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if reader.pos < reader.chars.len() {
+        return Err(reader.error("Expected the end of the document"));
+    }
+    Ok(value)
+}
+
+//arrays and objects nested deeper than this are refused before the reader runs out of stack
+const MAX_DEPTH: usize = 256;
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    depth: usize, // arrays and objects open around the current position
+}
+
+impl Reader {
+    fn error(&self, message: &str) -> String {
+        let found = match self.chars.get(self.pos) {
+            Some(c) => format!("'{}'", c),
+            None => "the end of the file".to_string(),
+        };
+        format!("JSON Error: {} at line {}, found {}.", message, self.line, found)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.get(self.pos) {
+            if !matches!(c, ' ' | '\t' | '\n' | '\r') {
+                break;
+            }
+            if c == '\n' {
+                self.line += 1;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.chars.get(self.pos) == Some(&expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for expected in word.chars() {
+            if !self.eat(expected) {
+                return Err(self.error(&format!("Expected '{}'", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.chars.get(self.pos) {
+            Some('{' | '[') if self.depth == MAX_DEPTH => Err(self.error(&format!("Nested deeper than {} levels", MAX_DEPTH))),
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => self.string().map(Value::String),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Value, String>) -> Result<Value, String> {
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.get(self.pos) != Some(&'"') {
+                return Err(self.error("Expected a key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(':') {
+                return Err(self.error("Expected ':'"));
+            }
+            let value = self.value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(Value::Object(fields));
+            }
+            if !self.eat(',') {
+                return Err(self.error("Expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            if !self.eat(',') {
+                return Err(self.error("Expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            let c = match self.chars.get(self.pos) {
+                Some(&c) => c,
+                None => return Err(self.error("Unterminated string")),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escaped = self.chars.get(self.pos).copied();
+                    self.pos += 1;
+                    match escaped {
+                        Some('"') => text.push('"'),
+                        Some('\\') => text.push('\\'),
+                        Some('/') => text.push('/'),
+                        Some('b') => text.push('\u{8}'),
+                        Some('f') => text.push('\u{c}'),
+                        Some('n') => text.push('\n'),
+                        Some('r') => text.push('\r'),
+                        Some('t') => text.push('\t'),
+                        Some('u') => text.push(self.unicode_escape()?),
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error("Unknown escape in string"));
+                        }
+                    }
+                }
+                c if (c as u32) < 0x20 => {
+                    self.pos -= 1;
+                    return Err(self.error("Control character in string"));
+                }
+                c => text.push(c),
+            }
+        }
+    }
+
+    //\uXXXX, characters outside the basic plane are written as a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("Invalid \\u escape"));
+        }
+        if !(self.eat('\\') && self.eat('u')) {
+            return Err(self.error("Expected the second half of a surrogate pair"));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("Invalid surrogate pair"));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or_else(|| self.error("Invalid \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.iter().skip(self.pos).take(4).collect();
+        match u32::from_str_radix(&digits, 16) {
+            Ok(n) if digits.len() == 4 => {
+                self.pos += 4;
+                Ok(n)
+            }
+            _ => Err(self.error("Expected four hex digits after \\u")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while let Some(&c) = self.chars.get(self.pos) {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        //1e999 reads as infinity, which json cannot write back
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+            Ok(_) => {
+                self.pos = start;
+                Err(self.error("Number out of range"))
+            }
+            Err(_) => {
+                self.pos = start;
+                Err(self.error("Invalid number"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_write() {
+        let value = parse(r#"{"name": "Fish \"&\" Chips\u00e9\ud83d\ude00", "sizes": [1, 2.5, -3], "ok": true, "none": null, "empty": {}}"#).unwrap();
+        assert_eq!(value.get("name").and_then(Value::as_str), Some("Fish \"&\" Chips\u{e9}\u{1f600}"));
+        assert_eq!(value.get("sizes").and_then(Value::as_array).map(|a| a.len()), Some(3));
+        assert_eq!(value.get("ok").and_then(Value::as_bool), Some(true));
+        assert_eq!(parse(&value.to_pretty_string()).unwrap(), value);
+        assert_eq!(
+            Value::Object(vec![("a".to_string(), Value::Array(vec![Value::Number(1.0), Value::String("x\ny".to_string())]))]).to_pretty_string(),
+            "{\n  \"a\": [\n    1,\n    \"x\\ny\"\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("{\"a\": 1,\n}").unwrap_err(), "JSON Error: Expected a key at line 2, found '}'.");
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("\"\\x\"").is_err());
+        assert!(parse("1 2").is_err());
+        assert!(parse("[1e999]").unwrap_err().contains("out of range"));
+        assert!(parse(&format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH))).is_ok());
+        assert!(parse(&"[".repeat(300_000)).unwrap_err().contains("Nested deeper than 256 levels"));
+        assert!(parse(&"{\"a\": ".repeat(MAX_DEPTH + 1)).is_err());
+        assert_eq!(Value::Array(vec![Value::Number(f64::INFINITY), Value::Number(f64::NAN)]).to_pretty_string(), "[\n  null,\n  null\n]");
+    }
+}
@@ -0,0 +1,267 @@
+//latex generation for printed reports, a standalone article: the head title goes into \title,
+//lists become itemize and media players become \url links, since paper cannot play them
+use crate::ast::{self, Document, Node};
+
+/// renders a parsed document as a complete .tex file
+pub fn render(document: &Document, keep_comments: bool) -> String {
+    let mut renderer = LatexRenderer { blocks: Vec::new(), current: String::new(), keep_comments };
+    renderer.render_nodes(&document.prologue);
+    if let Some(head) = &document.head {
+        renderer.render_nodes(head);
+    }
+    //the title is part of the preamble, everything else of the body
+    let preamble_blocks = std::mem::take(&mut renderer.blocks);
+    renderer.render_nodes(&document.body);
+    renderer.end_paragraph();
+
+    let title = document.head.iter().flatten()
+        .filter_map(|node| match node {
+            Node::Title(content) => Some(escape_text(ast::plain_text(content).trim())),
+            _ => None,
+        })
+        .find(|title| !title.is_empty());
+
+    let mut output = String::from("\\documentclass{article}\n\\usepackage[T1]{fontenc}\n\\usepackage[utf8]{inputenc}\n\\usepackage{hyperref}\n");
+    for block in &preamble_blocks {
+        output.push_str(block);
+        output.push('\n');
+    }
+    if let Some(title) = &title {
+        output.push_str(&format!("\\title{{{}}}\n\\author{{}}\n\\date{{}}\n", title));
+    }
+    output.push_str("\n\\begin{document}\n");
+    if title.is_some() {
+        output.push_str("\\maketitle\n");
+    }
+    for block in &renderer.blocks {
+        output.push('\n');
+        output.push_str(block);
+        output.push('\n');
+    }
+    output.push_str("\n\\end{document}\n");
+    output
+}
+
+struct LatexRenderer {
+    blocks: Vec<String>, // finished blocks, separated by a blank line in the output
+    current: String,     // the paragraph being written
+    keep_comments: bool,
+}
+
+impl LatexRenderer {
+    fn render_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.render_node(node);
+        }
+    }
+
+    fn render_node(&mut self, node: &Node) {
+        match node {
+            //the title goes into the preamble, see render
+            Node::Title(_) => {}
+            Node::Paragraph(content) => {
+                self.end_paragraph();
+                self.render_nodes(content);
+                self.end_paragraph();
+            }
+            Node::List(_) | Node::Item(_) => {
+                self.end_paragraph();
+                let items = match node {
+                    Node::List(items) => items.as_slice(),
+                    _ => std::slice::from_ref(node),
+                };
+                let list = self.list(items);
+                self.blocks.push(list);
+            }
+            Node::Audio(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!("Audio: \\url{{{}}}", escape_url(&m.address.text)));
+            }
+            Node::Video(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!("Video: \\url{{{}}}", escape_url(&m.address.text)));
+            }
+            //a variable's markup is rendered again at every use
+            Node::Variable { value, .. } => self.render_nodes(value),
+            //\\ with no line before it is an error in latex
+            Node::Newline if self.current.is_empty() => {}
+            Node::Comment(text) if self.keep_comments && self.current.is_empty() => {
+                self.end_paragraph();
+                self.blocks.push(comment(text));
+            }
+            _ => inline(&mut self.current, std::slice::from_ref(node), self.keep_comments),
+        }
+    }
+
+    //a \\ that ends a paragraph has no line to break, it is left out
+    fn end_paragraph(&mut self) {
+        let paragraph = std::mem::take(&mut self.current);
+        let mut paragraph = paragraph.trim();
+        while let Some(rest) = paragraph.strip_suffix("\\\\") {
+            paragraph = rest.trim_end().strip_suffix("\\leavevmode").unwrap_or(rest).trim_end();
+        }
+        if !paragraph.is_empty() {
+            self.blocks.push(paragraph.to_string());
+        }
+    }
+
+    fn list(&self, content: &[Node]) -> String {
+        let mut list = String::from("\\begin{itemize}\n");
+        for node in content {
+            match node {
+                Node::List(items) => {
+                    list.push_str(&self.list(items));
+                    list.push('\n');
+                }
+                //written after \item so text starting with [ is guarded, see opens_option
+                Node::Item(item) => {
+                    let mut line = String::from("\\item ");
+                    inline(&mut line, item, self.keep_comments);
+                    list.push_str(&format!("  {}\n", line.trim_end()));
+                }
+                Node::Comment(text) if self.keep_comments => {
+                    list.push_str(&comment(text));
+                    list.push('\n');
+                }
+                _ => {}
+            }
+        }
+        list.push_str("\\end{itemize}");
+        list
+    }
+}
+
+//inline content, blocks never appear inside a span or a list item
+fn inline(output: &mut String, nodes: &[Node], keep_comments: bool) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let escaped = escape_text(text);
+                if escaped.trim_start().starts_with('[') && opens_option(output) {
+                    output.push_str("{}");
+                }
+                output.push_str(&escaped);
+            }
+            Node::Bold(content) => command(output, "textbf", content, keep_comments),
+            Node::Italics(content) => command(output, "textit", content, keep_comments),
+            Node::Variable { value, .. } => inline(output, value, keep_comments),
+            //a \\ right after another one has no line to end, \leavevmode starts an empty one
+            Node::Newline if ends_with_break(output) => output.push_str("\\leavevmode\\\\\n"),
+            Node::Newline => output.push_str("\\\\\n"),
+            //% hides the rest of the line, including the line break, so the text after the
+            //comment joins the text before it the way the source had it
+            Node::Comment(text) if keep_comments => {
+                output.push_str(&comment(text));
+                output.push('\n');
+            }
+            _ => {}
+        }
+    }
+}
+
+fn command(output: &mut String, name: &str, content: &[Node], keep_comments: bool) {
+    let mut inner = String::new();
+    inline(&mut inner, content, keep_comments);
+    if !inner.is_empty() {
+        output.push_str(&format!("\\{}{{{}}}", name, inner));
+    }
+}
+
+//the last line of output that is not blank or a comment
+fn last_line(output: &str) -> &str {
+    output.lines().rev().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('%')).unwrap_or("")
+}
+
+//\\ and \item read a [ after them as an optional argument, even past spaces and comments
+fn opens_option(output: &str) -> bool {
+    let line = last_line(output);
+    line.ends_with("\\\\") || line.ends_with("\\item")
+}
+
+fn ends_with_break(output: &str) -> bool {
+    last_line(output).ends_with("\\\\")
+}
+
+//"% " before every line of the comment
+fn comment(text: &str) -> String {
+    text.trim().lines().map(|line| format!("% {}", line.trim())).collect::<Vec<_>>().join("\n")
+}
+
+/// text with the characters latex gives a meaning escaped
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// an address inside \url{...}, which takes most characters as they are. % and # are escaped
+/// for hyperref, a backslash or brace is percent-encoded since \url cannot take it at all
+pub fn escape_url(url: &str) -> String {
+    let mut escaped = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            '%' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\%5C"),
+            '{' => escaped.push_str("\\%7B"),
+            '}' => escaped.push_str("\\%7D"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_text("50% of $5 & {x_1} #1 ~ ^ \\"), "50\\% of \\$5 \\& \\{x\\_1\\} \\#1 \\textasciitilde{} \\textasciicircum{} \\textbackslash{}");
+        assert_eq!(escape_url("https://example.com/My%20Clips/a.mp4#t=5"), "https://example.com/My\\%20Clips/a.mp4\\#t=5");
+    }
+
+    #[test]
+    fn test_render_list_breaks_and_comments() {
+        let source = "#HAI #MAEK HEAD #GIMMEH TITLE 100% #MKAY #OIC #MAEK PARAGRAF #GIMMEH NEWLINE one #GIMMEH NEWLINE two #GIMMEH NEWLINE #OIC
+            #MAEK LIST #GIMMEH ITEM #GIMMEH BOLD fish #MKAY & chips #MKAY #OBTW more
+            later #TLDR #OIC #GIMMEH SOUNDZ theme.mp3 #MKAY #KTHXBYE";
+        let document = testing::parse(source);
+
+        let latex = render(&document, true);
+        assert!(latex.contains("\\begin{document}\n\\maketitle\n\none\\\\\ntwo\n\n\\begin{itemize}\n  \\item \\textbf{fish} \\& chips\n% more\n% later\n\\end{itemize}\n\nAudio: \\url{theme.mp3}\n\n\\end{document}\n"), "{}", latex);
+        assert!(latex.contains("\\title{100\\%}\n"));
+    }
+
+    //a [ right after \\ or \item would be read as an optional argument, and a \\ right after
+    //another one has no line to end
+    #[test]
+    fn test_brackets_and_repeated_breaks() {
+        let source = "#HAI #MAEK HEAD #GIMMEH TITLE t #MKAY #OIC
+            #MAEK PARAGRAF [a] #GIMMEH NEWLINE [b] #GIMMEH NEWLINE #GIMMEH NEWLINE c #GIMMEH NEWLINE #GIMMEH NEWLINE #OIC
+            #MAEK LIST #GIMMEH ITEM [x] #MKAY #GIMMEH ITEM y [z] #MKAY #OIC #KTHXBYE";
+        let latex = render(&testing::parse(source), false);
+        assert!(latex.contains("\n[a]\\\\\n{}[b]\\\\\n\\leavevmode\\\\\nc\n\n"), "{}", latex);
+        assert!(latex.contains("  \\item {}[x]\n  \\item y [z]\n"), "{}", latex);
+    }
+
+    //tests/TestN.tex is the expected output for tests/TestN.lol, comments left out
+    #[test]
+    fn test_golden_files() {
+        testing::check_golden_files("tex", |document| render(document, false));
+    }
+}
@@ -1,26 +1,62 @@
 use std::iter::Peekable;
 use std::str::Chars;
+use crate::suggest;
 
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    Hai, Kthxbye, Obtw, Tldr, Maek, Oic, Gimmeh, Mkay, Head, Title, 
+    Hai, Kthxbye, Maek, Oic, Gimmeh, Mkay, Head, Title, 
     Paragraf, Bold, Italics, List, Item, Newline,
     Soundz(String), Vidz(String), IHaz, ItIz, LemmeSee, I,
-    VarDef(String), VarVal(String), Text(String),
+    VarDef(String), VarVal(String), VarUse(String), Text(String), //VarDef/VarUse hold an <id>
+    Comment(String), //raw text between #OBTW and #TLDR
 }
 
-
+/// words that can follow '#'
+pub const ANNOTATIONS: [&str; 14] = [
+    "HAI", "KTHXBYE", "OBTW", "TLDR", "MAEK", "OIC", "MKAY",
+    "GIMMEH", "I", "IT", "LEMME", "HEAD", "PARAGRAF", "LIST",
+];
+/// words that can follow #GIMMEH
+pub const GIMMEH_ELEMENTS: [&str; 7] = ["BOLD", "ITALICS", "TITLE", "SOUNDZ", "VIDZ", "ITEM", "NEWLINE"];
+
+//words from html and other languages that are not typos but mean a lolcode keyword
+const ANNOTATION_ALIASES: [(&str, &str); 5] = [("END", "OIC"), ("CLOSE", "OIC"), ("BYE", "KTHXBYE"), ("HELLO", "HAI"), ("COMMENT", "OBTW")];
+const GIMMEH_ALIASES: [(&str, &str); 8] = [
+    ("B", "BOLD"), ("STRONG", "BOLD"), ("EM", "ITALICS"), ("LI", "ITEM"),
+    ("BR", "NEWLINE"), ("AUDIO", "SOUNDZ"), ("VIDEO", "VIDZ"), ("H1", "TITLE"),
+];
+
+#[allow(dead_code)]
 pub trait LexicalAnalyzer {
     fn get_char(&mut self) -> Option<char>;
     fn add_char(&mut self, c: char);
     fn lookup(&self, s: &str) -> bool;
 }
 
+/// where a token is in the source, start and end are byte offsets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize, //line the token starts on
+}
+
+/// a token and the part of the source it was read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned {
+    pub token: Token,
+    pub span: Span,
+}
+
 /// primary lexer struct, does conversion of strings to tokens
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
     pub line: usize,
+    offset: usize, //byte offset of the next char
+    space_start: Option<(usize, usize)>, //offset and line where the whitespace before the next word began
+    #[allow(dead_code)]
     current_lexeme: String,
 }
 
@@ -29,129 +65,204 @@ impl<'a> Lexer<'a> {
         Lexer {
             input: source.chars().peekable(),
             line: 1,
+            offset: 0,
+            space_start: None,
             current_lexeme: String::new(),
         }
     }
 
     ///main function for creating tokens with input, character by character
-    /// also looks for # and general text
+    /// also looks for # and general text, stops at the first error
+    #[allow(dead_code)]
     pub fn lex(&mut self) -> Result<Vec<Token>, String> {
         let mut tokens = Vec::new();
+        while self.peek_char().is_some() {
+            self.lex_next(&mut tokens)?;
+        }
+        Ok(tokens.into_iter().map(|spanned| spanned.token).collect())
+    }
 
-        while let Some(&c) = self.peek_char() {
-            if c.is_whitespace() {
-                self.get_char();
-                continue;
+    ///keeps going after a lexical error so tools can still see the whole file,
+    ///the text that could not be read is returned as an error span
+    pub fn lex_recovering(&mut self) -> (Vec<Spanned>, Vec<(Span, String)>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        while self.peek_char().is_some() {
+            let (start, line) = (self.offset, self.line);
+            if let Err(e) = self.lex_next(&mut tokens) {
+                //tokens read before the error in this step keep their place
+                let start = tokens.last().map_or(start, |t: &Spanned| t.span.end.max(start));
+                errors.push((Span { start, end: self.offset, line }, e));
             }
+        }
+        (tokens, errors)
+    }
 
-            // for words with # 
-            if c == '#' {
-                self.get_char(); 
-
-                let annotation_core = self.consume_annotation_core(); 
-                let ann_upper = annotation_core.to_uppercase();
-
-                
-                match ann_upper.as_str() { 
-                    "HAI" => tokens.push(Token::Hai),
-                    "KTHXBYE" => tokens.push(Token::Kthxbye),
-                    "OBTW" => tokens.push(Token::Obtw),
-                    "TLDR" => tokens.push(Token::Tldr),
-                    "MAEK" => tokens.push(Token::Maek),
-                    "OIC" => tokens.push(Token::Oic),
-                    "MKAY" => tokens.push(Token::Mkay),
-
-                    "GIMMEH" => { //structure for words with #
-                        tokens.push(Token::Gimmeh);
-                        let next_word = self.consume_word().to_uppercase(); //consumes GIMMEH command
-                        match next_word.as_str() {
-                            "BOLD" | "ITALICS" | "TITLE" => {
-                                self.skip_whitespace(); //Consumes text,
-                                let text = self.consume_text();
-                                //pushes bold/italic/title tokens
-                                match next_word.as_str() {
-                                    "BOLD" => tokens.push(Token::Bold),
-                                    "ITALICS" => tokens.push(Token::Italics),
-                                    "TITLE" => tokens.push(Token::Title),
-                                    _ => {}
-                                }
-                                if !text.is_empty() {
-                                    tokens.push(Token::Text(text)); //pushed seperate so parser can look for closing tag
-                                }
-                            }// Rest of Gimmeh portions, follow similar format
-                            "SOUNDZ" | "VIDZ" => {
-                                self.skip_whitespace();
-                                let address = self.consume_word();
-                                match next_word.as_str() {
-                                    "SOUNDZ" => tokens.push(Token::Soundz(address)),
-                                    "VIDZ" => tokens.push(Token::Vidz(address)),
-                                    _ => {}
-                                }
+    //reads one whitespace character, or one word or annotation and the tokens it makes
+    fn lex_next(&mut self, tokens: &mut Vec<Spanned>) -> Result<(), String> {
+        let c = match self.peek_char() {
+            Some(&c) => c,
+            None => return Ok(()),
+        };
+        if c.is_whitespace() {
+            if self.space_start.is_none() {
+                self.space_start = Some((self.offset, self.line));
+            }
+            self.get_char();
+            return Ok(());
+        }
+        let space = self.space_start.take();
+        let mark = self.mark();
+
+        // for words with # 
+        if c == '#' {
+            self.get_char(); 
+
+            let annotation_core = self.consume_annotation_core(); 
+            let ann_upper = annotation_core.to_uppercase();
+
+            //two inline elements with whitespace between them, e.g. #MKAY #LEMME SEE
+            if let Some((space_offset, space_line)) = space {
+                let after_mkay = tokens.last().map(|t| &t.token) == Some(&Token::Mkay);
+                if after_mkay && matches!(ann_upper.as_str(), "GIMMEH" | "LEMME") {
+                    let span = Span { start: space_offset, end: mark.0, line: space_line };
+                    tokens.push(Spanned { token: Token::Text(" ".to_string()), span });
+                }
+            }
+            
+            match ann_upper.as_str() { 
+                "HAI" => self.push(tokens, Token::Hai, mark),
+                "KTHXBYE" => self.push(tokens, Token::Kthxbye, mark),
+                "OBTW" => {
+                    let comment = self.consume_comment()?;
+                    self.push(tokens, comment, mark);
+                }
+                "TLDR" => return Err(format!("Found '#TLDR' without an opening '#OBTW' on line {}", self.line)),
+                "MAEK" => self.push(tokens, Token::Maek, mark),
+                "OIC" => self.push(tokens, Token::Oic, mark),
+                "MKAY" => self.push(tokens, Token::Mkay, mark),
+
+                "GIMMEH" => { //structure for words with #
+                    self.push(tokens, Token::Gimmeh, mark);
+                    self.skip_whitespace();
+                    let element_mark = self.mark();
+                    let next_word = self.consume_word().to_uppercase(); //consumes GIMMEH command
+                    match next_word.as_str() {
+                        "BOLD" | "ITALICS" | "TITLE" => {
+                            //pushes bold/italic/title tokens
+                            match next_word.as_str() {
+                                "BOLD" => self.push(tokens, Token::Bold, element_mark),
+                                "ITALICS" => self.push(tokens, Token::Italics, element_mark),
+                                _ => self.push(tokens, Token::Title, element_mark),
+                            }
+                            self.skip_whitespace(); //Consumes text,
+                            let text_mark = self.mark();
+                            let text = self.consume_text();
+                            if !text.is_empty() {
+                                self.push(tokens, Token::Text(text), text_mark); //pushed seperate so parser can look for closing tag
+                            }
+                        }// Rest of Gimmeh portions, follow similar format
+                        "SOUNDZ" | "VIDZ" => {
+                            let address = self.consume_address();
+                            match next_word.as_str() {
+                                "SOUNDZ" => self.push(tokens, Token::Soundz(address), element_mark),
+                                _ => self.push(tokens, Token::Vidz(address), element_mark),
                             }
-                            "ITEM" => tokens.push(Token::Item),
-                            "NEWLINE" => tokens.push(Token::Newline),
-                            _ => return Err(format!("Unknown GIMMEH command '{}' on line {}", next_word, self.line)),
                         }
+                        "ITEM" => self.push(tokens, Token::Item, element_mark),
+                        "NEWLINE" => self.push(tokens, Token::Newline, element_mark),
+                        _ => {
+                            let hint = keyword_hint(&next_word, &GIMMEH_ELEMENTS, &GIMMEH_ALIASES, "#GIMMEH ");
+                            return Err(format!("Unknown GIMMEH command '{}' on line {}{}", next_word, self.line, hint));
+                        }
+                    }
+                }
+                //For I Haz 
+                "I" => { 
+                    if self.consume_word().to_uppercase() == "HAZ" {
+                        self.push(tokens, Token::IHaz, mark);
+                        let (var_name, span) = self.consume_identifier("#I HAZ")?;
+                        tokens.push(Spanned { token: Token::VarDef(var_name), span });
+                    } else {
+                        return Err(format!("Expected 'HAZ' after '#I' on line {}", self.line));
                     }
-                    //For I Haz 
-                    "I" => { 
-                        if self.consume_word().to_uppercase() == "HAZ" {
-                            tokens.push(Token::IHaz);
-                            // self.skip_whitespace();
-                            // let var_name = self.consume_word();
-                            // tokens.push(Token::VarDef(var_name)); when uncommented the test at bottom work but messes up actual test cases
-                        } else {
-                            return Err(format!("Expected 'HAZ' after '#I' on line {}", self.line));
+                } 
+                "IT" => { //if IZ doesnt follow then returns error
+                    if self.consume_word().to_uppercase() == "IZ" {
+                        self.push(tokens, Token::ItIz, mark);
+                        //the text up to the first # of the value, markup may follow it
+                        self.skip_whitespace();
+                        let (value_start, value_line) = self.mark();
+                        let mut var_value = self.consume_text();
+                        if self.next_annotation_is("MKAY") {
+                            var_value.truncate(var_value.trim_end().len());
                         }
-                    } 
-                    "IT" => { //if IZ doesnt follow then returns error
-                        if self.consume_word().to_uppercase() == "IZ" {
-                            tokens.push(Token::ItIz);
-                            // self.skip_whitespace();
-                            // let var_value = self.consume_word();
-                            // tokens.push(Token::VarVal(var_value)); same as above, messes up test cases but fixes bottom ones
-                        } else {
-                            return Err(format!("Expected 'IZ' after '#IT' on line {}", self.line));
+                        if !var_value.is_empty() {
+                            let span = Span { start: value_start, end: value_start + var_value.len(), line: value_line };
+                            tokens.push(Spanned { token: Token::VarVal(var_value), span });
                         }
+                    } else {
+                        return Err(format!("Expected 'IZ' after '#IT' on line {}", self.line));
                     }
-                    "LEMME" => { 
-                        if self.consume_word().to_uppercase() == "SEE" {
-                            tokens.push(Token::LemmeSee);
-                        } else {
-                            return Err(format!("Expected 'SEE' after '#LEMME' on line {}", self.line));
-                        }
+                }
+                "LEMME" => { 
+                    if self.consume_word().to_uppercase() == "SEE" {
+                        self.push(tokens, Token::LemmeSee, mark);
+                        let (var_name, span) = self.consume_identifier("#LEMME SEE")?;
+                        tokens.push(Spanned { token: Token::VarUse(var_name), span });
+                    } else {
+                        return Err(format!("Expected 'SEE' after '#LEMME' on line {}", self.line));
                     }
+                }
 
-                    "HEAD" => tokens.push(Token::Head),
-                    "PARAGRAF" => tokens.push(Token::Paragraf),
-                    "LIST" => tokens.push(Token::List),
+                "HEAD" => self.push(tokens, Token::Head, mark),
+                "PARAGRAF" => self.push(tokens, Token::Paragraf, mark),
+                "LIST" => self.push(tokens, Token::List, mark),
 
-                    _ => return Err(format!("Unknown annotation '#{}' on line {}", annotation_core, self.line)),
+                _ => {
+                    let hint = keyword_hint(&annotation_core, &ANNOTATIONS, &ANNOTATION_ALIASES, "#");
+                    return Err(format!("Unknown annotation '#{}' on line {}{}", annotation_core, self.line, hint));
                 }
-            } else { //text without #, should fix ambiguity
-                let word = self.consume_word();
-                let upper_word = word.to_uppercase();
-
-                match upper_word.as_str() { 
-                    //Tokens that dont have a # that need to be found
-                    "PARAGRAF" => tokens.push(Token::Paragraf),
-                    "LIST" => tokens.push(Token::List),
-                    "HEAD" => tokens.push(Token::Head),
-
-                   
-                    _ => {
-                        //text for paragraph conent
-                        let mut text = word; 
-                        text.push_str(&self.consume_text());
-                        
-                        if !text.is_empty() {
-                            tokens.push(Token::Text(text));
-                        }
+            }
+        } else { //text without #, should fix ambiguity
+            let word = self.consume_word();
+            let upper_word = word.to_uppercase();
+
+            match upper_word.as_str() { 
+                //Tokens that dont have a # that need to be found
+                "PARAGRAF" => self.push(tokens, Token::Paragraf, mark),
+                "LIST" => self.push(tokens, Token::List, mark),
+                "HEAD" => self.push(tokens, Token::Head, mark),
+
+               
+                _ => {
+                    //text for paragraph conent, a space after the end of an inline element separates it
+                    //from the text, after an opening keyword it is only a delimiter
+                    let after_element = matches!(tokens.last().map(|t| &t.token), Some(Token::Mkay) | Some(Token::Comment(_)));
+                    let (mut text, text_mark) = match space {
+                        Some(space_mark) if after_element => (format!(" {}", word), space_mark),
+                        _ => (word, mark),
+                    };
+                    text.push_str(&self.consume_text());
+                    
+                    if !text.is_empty() {
+                        self.push(tokens, Token::Text(text), text_mark);
                     }
                 }
             }
-        } 
-        Ok(tokens)
+        }
+        Ok(())
+    }
+
+    //offset and line of the next char
+    fn mark(&self) -> (usize, usize) {
+        (self.offset, self.line)
+    }
+
+    //adds a token that started at mark and ends at the current position
+    fn push(&self, tokens: &mut Vec<Spanned>, token: Token, mark: (usize, usize)) {
+        let (start, line) = mark;
+        tokens.push(Spanned { token, span: Span { start, end: self.offset, line } });
     }
 
 // Helper methods below
@@ -161,10 +272,11 @@ impl<'a> Lexer<'a> {
     }
 
    
+    //keywords are letters only, so "#MKAY/intro.mp4" ends the annotation at the '/'
     fn consume_annotation_core(&mut self) -> String {
         let mut s = String::new();
         while let Some(&c) = self.peek_char() {
-            if c.is_whitespace() || c == '#' { 
+            if !c.is_alphanumeric() { 
                 break;
             }
             s.push(c);
@@ -172,6 +284,46 @@ impl<'a> Lexer<'a> {
         }
         s
     }
+    //<id> ::= <letter> <id_rest>, the name runs up to the next # and surrounding whitespace is dropped
+    fn consume_identifier(&mut self, after: &str) -> Result<(String, Span), String> {
+        self.skip_whitespace();
+        let (start, line) = self.mark();
+        let name = self.consume_text().trim_end().to_string();
+        if name.is_empty() {
+            return Err(format!("Expected a variable name after '{}' on line {}", after, line));
+        }
+        if !is_identifier(&name) {
+            return Err(format!(
+                "Invalid variable name '{}' on line {}: a name is a letter followed by letters and digits, with no spaces",
+                name, line
+            ));
+        }
+        Ok((name.clone(), Span { start, end: start + name.len(), line }))
+    }
+    //looks past the next '#' without consuming anything
+    fn next_annotation_is(&self, keyword: &str) -> bool {
+        let mut rest = self.input.clone();
+        if rest.next() != Some('#') {
+            return false;
+        }
+        let word: String = rest.take_while(|c| c.is_alphanumeric()).collect();
+        word.eq_ignore_ascii_case(keyword)
+    }
+    //comments are opaque, everything up to #TLDR is kept as written, including other #words.
+    //#TLDR only closes the comment as a whole word, #TLDRS does not
+    fn consume_comment(&mut self) -> Result<Token, String> {
+        let start_line = self.line;
+        let mut s = String::new();
+        while let Some(c) = self.get_char() {
+            s.push(c);
+            let closes = s.get(s.len().saturating_sub(5)..).is_some_and(|t| t.eq_ignore_ascii_case("#TLDR"));
+            if closes && !self.peek_char().is_some_and(|c| c.is_alphanumeric()) {
+                s.truncate(s.len() - 5);
+                return Ok(Token::Comment(s));
+            }
+        }
+        Err(format!("Unterminated comment: '#OBTW' on line {} is never closed with '#TLDR'", start_line))
+    }
 //consumes characters that form a single word and stops at whitespace or # 
     fn consume_word(&mut self) -> String {
         let mut s = String::new();
@@ -190,6 +342,27 @@ impl<'a> Lexer<'a> {
         s
     }
 
+    //a media address reads up to whitespace. a # inside it starts the #fragment, unless it begins
+    //an annotation written without a space before it, e.g. a.mp3#MKAY
+    fn consume_address(&mut self) -> String {
+        let mut s = String::new();
+        self.skip_whitespace();
+        while let Some(&c) = self.peek_char() {
+            if c.is_whitespace() || (c == '#' && (s.is_empty() || self.annotation_follows())) {
+                break;
+            }
+            s.push(c);
+            self.get_char();
+        }
+        s
+    }
+
+    //true when the # about to be read starts an annotation
+    fn annotation_follows(&self) -> bool {
+        let word: String = self.input.clone().skip(1).take_while(|c| c.is_alphanumeric()).collect();
+        ANNOTATIONS.contains(&word.to_uppercase().as_str())
+    }
+
     //consumes all characters until it reaches a #
     fn consume_text(&mut self) -> String {
         let mut s = String::new();
@@ -218,6 +391,9 @@ impl<'a> Lexer<'a> {
 impl<'a> LexicalAnalyzer for Lexer<'a> {
     fn get_char(&mut self) -> Option<char> {
         let c = self.input.next();
+        if let Some(c) = c {
+            self.offset += c.len_utf8();
+        }
         if c == Some('\n') {
             self.line += 1;
         }
@@ -229,12 +405,31 @@ impl<'a> LexicalAnalyzer for Lexer<'a> {
     }
 
     fn lookup(&self, s: &str) -> bool {
-        matches!(
-            s.to_uppercase().as_str(),
-            "#HAI" | "#KTHXBYE" | "#OBTW" | "#TLDR" | "#MAEK" | "#OIC" | "#MKAY"
-                | "#GIMMEH" | "#I" | "#IT" | "#LEMME"
-                | "#HEAD" | "#PARAGRAF" | "#LIST"
-        )
+        let upper = s.to_uppercase();
+        upper.strip_prefix('#').is_some_and(|word| ANNOTATIONS.contains(&word))
+    }
+}
+
+/// true for an <id>: an ascii letter followed by ascii letters and digits
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic()) && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+//" (did you mean `#OIC`?)" for an unknown keyword, aliases win over spelling
+fn keyword_hint(word: &str, keywords: &[&str], aliases: &[(&str, &str)], prefix: &str) -> String {
+    let upper = word.to_uppercase();
+    let keyword = aliases.iter()
+        .find(|(alias, _)| *alias == upper)
+        .map(|(_, keyword)| *keyword)
+        .or_else(|| suggest::closest(word, keywords.iter().copied()));
+    //the two word annotations are suggested whole
+    match keyword {
+        Some("I") => format!(" (did you mean `{}I HAZ`?)", prefix),
+        Some("IT") => format!(" (did you mean `{}IT IZ`?)", prefix),
+        Some("LEMME") => format!(" (did you mean `{}LEMME SEE`?)", prefix),
+        Some(keyword) => format!(" (did you mean `{}{}`?)", prefix, keyword),
+        None => String::new(),
     }
 }
 //assert_eq tests, didnt know if we had to write them ourselves or not
@@ -280,6 +475,77 @@ mod tests {
         assert_eq!(actual_tokens, expected_tokens); 
     }
 
+    #[test]
+    fn test_variable_names_are_identifiers() {
+        let tokens = Lexer::new("#LEMME SEE  answer2 \n#MKAY").lex().unwrap();
+        assert_eq!(tokens, vec![Token::LemmeSee, Token::VarUse("answer2".to_string()), Token::Mkay]);
+
+        let error = Lexer::new("#I HAZ the answer #IT IZ 42 #MKAY").lex().unwrap_err();
+        assert!(error.contains("Invalid variable name 'the answer'"), "{}", error);
+        assert!(Lexer::new("#I HAZ 42 #IT IZ x #MKAY").lex().is_err());
+        assert!(Lexer::new("#I HAZ #IT IZ x #MKAY").lex().is_err());
+    }
+
+    #[test]
+    fn test_space_after_inline_element_is_kept() {
+        let tokens = Lexer::new("#GIMMEH BOLD life #MKAY  is #GIMMEH ITALICS x #MKAY.").lex().unwrap();
+        assert_eq!(tokens[4], Token::Text(" is ".to_string()));
+        assert_eq!(tokens[9], Token::Text(".".to_string()));
+
+        let tokens = Lexer::new("#LEMME SEE a #MKAY #LEMME SEE b #MKAY").lex().unwrap();
+        assert_eq!(tokens[3], Token::Text(" ".to_string()));
+    }
+
+    #[test]
+    fn test_comment_is_one_token() {
+        let source = "#HAI #OBTW uses #END and #GIMMEH BOLD freely #tldr #KTHXBYE";
+        let mut lexer = Lexer::new(source);
+
+        let actual_tokens = lexer.lex().unwrap();
+
+        let expected_tokens = vec![
+            Token::Hai,
+            Token::Comment(" uses #END and #GIMMEH BOLD freely ".to_string()),
+            Token::Kthxbye,
+        ];
+
+        assert_eq!(actual_tokens, expected_tokens);
+    }
+
+    #[test]
+    fn test_comment_with_non_ascii_text() {
+        let tokens = Lexer::new("#OBTW ééé #TLDRxyz ünï #TLDR").lex().unwrap();
+        assert_eq!(tokens, vec![Token::Comment(" ééé #TLDRxyz ünï ".to_string())]);
+    }
+
+    #[test]
+    fn test_fragment_in_media_address() {
+        let tokens = Lexer::new("#GIMMEH SOUNDZ a.mp3#t=5 #MKAY #GIMMEH VIDZ b.mp4#MKAY").lex().unwrap();
+        assert_eq!(tokens, vec![
+            Token::Gimmeh, Token::Soundz("a.mp3#t=5".to_string()), Token::Mkay, Token::Text(" ".to_string()),
+            Token::Gimmeh, Token::Vidz("b.mp4".to_string()), Token::Mkay,
+        ]);
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let source = "#HAI\n\n#OBTW never closed\n#KTHXBYE";
+        let mut lexer = Lexer::new(source);
+
+        let error = lexer.lex().unwrap_err();
+
+        assert!(error.contains("line 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_unknown_keyword_suggestions() {
+        let error = Lexer::new("#HAI #GIMMEH BOLDD x #MKAY").lex().unwrap_err();
+        assert!(error.ends_with("(did you mean `#GIMMEH BOLD`?)"), "{}", error);
+
+        let error = Lexer::new("#HAI #END").lex().unwrap_err();
+        assert!(error.ends_with("(did you mean `#OIC`?)"), "{}", error);
+    }
+
    #[test]
     fn test_complex_inline_text() {
         let source = "#MAEK PARAGRAF Sample text #OIC";
@@ -0,0 +1,244 @@
+//lint pass over the parsed document, finds code that compiles but is probably a mistake
+use crate::ast::{self, Document, Node};
+use crate::scope::Scopes;
+
+/// the kinds of lint warning, each one can be turned off with --allow <id>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LintId {
+    UnusedVariable,
+    RedefinedVariable,
+    ShadowedGlobal,
+    EmptyParagraph,
+    EmptySpan,
+    TrailingContent,
+}
+
+pub const ALL_LINTS: [LintId; 6] = [
+    LintId::UnusedVariable,
+    LintId::RedefinedVariable,
+    LintId::ShadowedGlobal,
+    LintId::EmptyParagraph,
+    LintId::EmptySpan,
+    LintId::TrailingContent,
+];
+
+impl LintId {
+    /// the name used in warnings and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            LintId::UnusedVariable => "unused-variable",
+            LintId::RedefinedVariable => "redefined-variable",
+            LintId::ShadowedGlobal => "shadowed-global",
+            LintId::EmptyParagraph => "empty-paragraph",
+            LintId::EmptySpan => "empty-span",
+            LintId::TrailingContent => "trailing-content",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LintId> {
+        ALL_LINTS.iter().copied().find(|id| id.name() == name)
+    }
+}
+
+//what the linter remembers about a definition
+struct Binding {
+    order: usize, //definitions are reported in the order they were written
+    used: bool,
+}
+
+/// runs every lint that is not in allowed and returns the warnings
+pub fn check(document: &Document, allowed: &[LintId]) -> Vec<String> {
+    let mut linter = Linter { scopes: Scopes::new(), defined: 0, found: Vec::new() };
+    linter.walk(&document.prologue);
+    if let Some(head) = &document.head {
+        linter.walk(head);
+    }
+    linter.walk(&document.body);
+    let globals = linter.scopes.pop();
+    linter.report_unused(globals);
+    for comment in &document.trailing {
+        linter.warn(LintId::TrailingContent, format!("Content after #KTHXBYE is ignored: #OBTW{}#TLDR", comment));
+    }
+
+    linter.found.into_iter()
+        .filter(|(id, _)| !allowed.contains(id))
+        .map(|(id, message)| format!("Warning [{}]: {}", id.name(), message))
+        .collect()
+}
+
+struct Linter {
+    scopes: Scopes<Binding>,
+    defined: usize,
+    found: Vec<(LintId, String)>,
+}
+
+impl Linter {
+    fn warn(&mut self, id: LintId, message: String) {
+        self.found.push((id, message));
+    }
+
+    fn walk(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.visit(node);
+        }
+    }
+
+    fn visit(&mut self, node: &Node) {
+        match node {
+            Node::Paragraph(content) => {
+                if is_blank(content) {
+                    self.warn(LintId::EmptyParagraph, "Paragraph has no content.".to_string());
+                }
+                self.walk_block(content);
+            }
+            Node::List(content) | Node::Item(content) => self.walk_block(content),
+            Node::Bold(content) | Node::Italics(content) => {
+                if is_blank(content) {
+                    let kind = if matches!(node, Node::Bold(_)) { "BOLD" } else { "ITALICS" };
+                    self.warn(LintId::EmptySpan, format!("#GIMMEH {} has no text.", kind));
+                }
+                self.walk(content);
+            }
+            Node::Title(content) => self.walk(content),
+            Node::Define { name, value } => {
+                //the value is read before the name exists, so #LEMME SEE in it sees the outer definition
+                self.walk(value);
+                self.define(name);
+            }
+            //the value was already checked where it was defined
+            Node::Variable { name, .. } => {
+                if let Some(binding) = self.scopes.lookup_mut(name) {
+                    binding.used = true;
+                }
+            }
+            Node::Audio(media) | Node::Video(media) => self.walk(&media.variables),
+            Node::Text(_) | Node::Newline | Node::Comment(_) => {}
+        }
+    }
+
+    //paragraphs, lists and list items each have their own frame, like in the parser
+    fn walk_block(&mut self, content: &[Node]) {
+        self.scopes.push();
+        self.walk(content);
+        let frame = self.scopes.pop();
+        self.report_unused(frame);
+    }
+
+    fn define(&mut self, name: &str) {
+        if self.scopes.lookup_local(name).is_some() {
+            self.warn(LintId::RedefinedVariable, format!("Variable '{}' is defined again in the same block; the first value is never used.", name));
+        } else if !self.scopes.is_global() && self.scopes.lookup_global(name).is_some() {
+            self.warn(LintId::ShadowedGlobal, format!("Variable '{}' hides the global variable of the same name inside this block.", name));
+        }
+
+        //a replaced definition is reported as redefined, not as unused
+        self.scopes.define(name.to_string(), Binding { order: self.defined, used: false });
+        self.defined += 1;
+    }
+
+    fn report_unused(&mut self, frame: Vec<(String, Binding)>) {
+        let mut unused: Vec<(String, Binding)> = frame.into_iter().filter(|(_, b)| !b.used).collect();
+        unused.sort_by_key(|(_, b)| b.order);
+        for (name, _) in unused {
+            self.warn(LintId::UnusedVariable, format!("Variable '{}' is defined but never used.", name));
+        }
+    }
+}
+
+//nothing that would show up on the page, only whitespace, definitions and comments
+fn is_blank(content: &[Node]) -> bool {
+    content.iter().all(|node| match node {
+        Node::Text(_) | Node::Variable { .. } => ast::plain_text(std::slice::from_ref(node)).trim().is_empty(),
+        Node::Define { .. } | Node::Comment(_) => true,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cst::Cst;
+    use crate::parser::Parser;
+    use crate::testing;
+
+    fn text(s: &str) -> Node {
+        Node::Text(s.to_string())
+    }
+
+    fn define(name: &str, value: &str) -> Node {
+        Node::Define { name: name.to_string(), value: vec![text(value)] }
+    }
+
+    fn use_of(name: &str, value: &str) -> Node {
+        Node::Variable { name: name.to_string(), value: vec![text(value)] }
+    }
+
+    #[test]
+    fn test_scoping_lints() {
+        //Test8.lol: a paragraph redefines the global answer
+        let document = Document {
+            prologue: Vec::new(),
+            head: None,
+            body: vec![
+                define("answer", "happiness"),
+                define("unused", "x"),
+                Node::Paragraph(vec![define("answer", "42"), define("answer", "43"), use_of("answer", "43")]),
+                Node::Paragraph(vec![use_of("answer", "happiness")]),
+            ],
+            trailing: Vec::new(),
+        };
+
+        let warnings = check(&document, &[]);
+        assert_eq!(warnings, vec![
+            "Warning [shadowed-global]: Variable 'answer' hides the global variable of the same name inside this block.",
+            "Warning [redefined-variable]: Variable 'answer' is defined again in the same block; the first value is never used.",
+            "Warning [unused-variable]: Variable 'unused' is defined but never used.",
+        ]);
+        assert_eq!(check(&document, &[LintId::ShadowedGlobal, LintId::RedefinedVariable]).len(), 1);
+    }
+
+    #[test]
+    fn test_empty_and_trailing_lints() {
+        let document = Document {
+            prologue: Vec::new(),
+            head: None,
+            body: vec![
+                Node::Paragraph(vec![text("  "), Node::Comment("todo".to_string())]),
+                Node::Paragraph(vec![Node::Bold(vec![text(" ")]), text("hi")]),
+            ],
+            trailing: vec![" bye ".to_string()],
+        };
+
+        let warnings = check(&document, &[]);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].starts_with("Warning [empty-paragraph]"));
+        assert!(warnings[1].starts_with("Warning [empty-span]"));
+        assert!(warnings[2].starts_with("Warning [trailing-content]"));
+        assert_eq!(LintId::from_name("empty-span"), Some(LintId::EmptySpan));
+    }
+
+    //text after #KTHXBYE fails the compile, a comment there is only reported
+    #[test]
+    fn test_content_after_kthxbye() {
+        let error = Parser::new(Cst::parse("#HAI #KTHXBYE bye").tokens()).parse().unwrap_err();
+        assert_eq!(error, "Syntax Error: Content found after #KTHXBYE: Text(\"bye\")");
+
+        let document = testing::parse("#HAI #KTHXBYE\n#OBTW bye #TLDR\n#OBTW for now #TLDR\n");
+        assert_eq!(document.trailing, [" bye ", " for now "]);
+        assert_eq!(check(&document, &[]), vec![
+            "Warning [trailing-content]: Content after #KTHXBYE is ignored: #OBTW bye #TLDR",
+            "Warning [trailing-content]: Content after #KTHXBYE is ignored: #OBTW for now #TLDR",
+        ]);
+    }
+
+    //a variable read into a media address or option is a use, the address only keeps its text
+    #[test]
+    fn test_variable_used_in_media_address() {
+        let document = testing::parse("#HAI #I HAZ base #IT IZ clips #MKAY #I HAZ w #IT IZ 640 #MKAY
+            #GIMMEH VIDZ #LEMME SEE base #MKAY/intro.mp4 width=#LEMME SEE w #MKAY #MKAY #KTHXBYE");
+        let Node::Video(media) = &document.body[0] else { panic!("{:?}", document.body) };
+        assert_eq!(media.address.text, "clips/intro.mp4");
+        assert_eq!(media.variables, [use_of("base", "clips"), use_of("w", "640")]);
+        assert_eq!(check(&document, &[]), Vec::<String>::new());
+    }
+}
@@ -1,24 +1,311 @@
 use std::env;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+ mod address;
+ mod assets;
+ mod ast;
+ mod ast_json;
+ mod convert;
+ mod cst;
+ mod decompile;
+ mod dump;
+ mod epub;
+ mod fmt;
+ mod gemtext;
  mod parser;
  mod lexer;
+ mod html;
+ mod import;
+ mod json;
+ mod latex;
+ mod lint;
+ mod man;
+ mod markdown;
+ mod media;
+ mod output;
+ mod scope;
+ mod suggest;
+ mod text;
+ #[cfg(test)]
+ mod testing;
+ mod whitespace;
+ mod zip;
  use parser::Parser;
- use lexer::Lexer;
+ use cst::Cst;
+ use lint::{LintId, ALL_LINTS};
+ use output::{Emit, Format, RenderOptions, ALL_EMITS, ALL_FORMATS};
+
+ const USAGE: &str = "Usage: lolcompiler [--strict] [--keep-comments] [--format html|markdown|text|latex|gemtext|man] [--width <columns>] [--keep-emphasis] [--allow <lint>]... [--out-dir <folder> [--hash-assets]] <input_file.lol|.json>
+       lolcompiler --emit ast-json|tokens|tokens-jsonl <input_file.lol>
+       lolcompiler fmt [--check] <input_file.lol>...
+       lolcompiler import <input_file.md>...
+       lolcompiler decompile <input_file.html>...
+       lolcompiler epub [--title <book title>] --out <book.epub> <chapter.lol>...";
+
+ fn usage_error(message: &str) -> ! {
+    eprintln!("{}", USAGE);
+    eprintln!("Error: {}", message);
+    process::exit(1);
+ }
+
+ ///rewrites files in the canonical layout, with --check only reports the ones that would change
+ fn fmt_command(args: &[String]) -> ! {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if let Some(arg) = files.iter().find(|arg| arg.starts_with("--")) {
+        usage_error(&format!("Unknown option '{}'.", arg));
+    }
+    if files.is_empty() {
+        usage_error("fmt needs at least one input file name.");
+    }
+
+    let mut failed = false;
+    for file in files {
+        let source = match fs::read_to_string(file) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("File Error: Could not read file '{}'. {}", file, e);
+                failed = true;
+                continue;
+            }
+        };
+        //only files that compile are formatted, so a typo is never reprinted as something else
+        let cst = Cst::parse(&source);
+        if let Some(e) = cst.errors.first() {
+            eprintln!("{}: Lexer Error: {}", file, e);
+            failed = true;
+            continue;
+        }
+        if let Err(e) = Parser::new(cst.tokens()).parse() {
+            eprintln!("{}: {}", file, e);
+            failed = true;
+            continue;
+        }
+
+        let formatted = fmt::format(&cst);
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("Would reformat: {}", file);
+            failed = true;
+        } else if let Err(e) = fs::write(file, &formatted) {
+            eprintln!("File Error: Could not write file '{}'. {}", file, e);
+            failed = true;
+        } else {
+            println!("Formatted: {}", file);
+        }
+    }
+    process::exit(if failed { 1 } else { 0 });
+ }
+
+ ///converts each file to a .lol file next to it with convert, printing what could not be carried over
+ fn convert_command(command: &str, args: &[String], convert: fn(&str, &str) -> (String, Vec<String>)) -> ! {
+    if let Some(arg) = args.iter().find(|arg| arg.starts_with("--")) {
+        usage_error(&format!("Unknown option '{}'.", arg));
+    }
+    if args.is_empty() {
+        usage_error(&format!("{} needs at least one input file name.", command));
+    }
+
+    let mut failed = false;
+    for file in args {
+        let input = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("File Error: Could not read file '{}'. {}", file, e);
+                failed = true;
+                continue;
+            }
+        };
+        let output = Path::new(file).with_extension("lol");
+        //an existing .lol file may have been edited by hand since the last conversion
+        if output.exists() {
+            eprintln!("File Error: '{}' already exists, it was not overwritten.", output.display());
+            failed = true;
+            continue;
+        }
+        let name = output.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+        let (lol, warnings) = convert(&input, &name);
+        for warning in &warnings {
+            eprintln!("{}: {}", file, warning);
+        }
+        if let Err(e) = fs::write(&output, lol) {
+            eprintln!("File Error: Could not write file '{}'. {}", output.display(), e);
+            failed = true;
+        } else {
+            println!("Wrote: {} -> {}", file, output.display());
+        }
+    }
+    process::exit(if failed { 1 } else { 0 });
+ }
+
+ ///compiles the chapters in order and bundles them into one epub file
+ fn epub_command(args: &[String]) -> ! {
+    let mut out = None;
+    let mut title = None;
+    let mut chapters = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => match args.next() {
+                Some(file) => out = Some(file),
+                None => usage_error("--out needs the name of the .epub file."),
+            },
+            "--title" => match args.next() {
+                Some(text) => title = Some(text.clone()),
+                None => usage_error("--title needs the title of the book."),
+            },
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option '{}'.", arg)),
+            _ => chapters.push(arg),
+        }
+    }
+    let out = out.unwrap_or_else(|| usage_error("epub needs --out <book.epub>."));
+    if chapters.is_empty() {
+        usage_error("epub needs at least one chapter file.");
+    }
+    let stem = |file: &str| Path::new(file).file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+
+    let mut compiled = Vec::new();
+    for file in chapters {
+        let source = match fs::read_to_string(file) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("File Error: Could not read file '{}'. {}", file, e);
+                process::exit(1);
+            }
+        };
+        let cst = Cst::parse(&source);
+        if let Some(e) = cst.errors.first() {
+            eprintln!("{}: Lexer Error: {}", file, e);
+            process::exit(1);
+        }
+        let mut parser = Parser::new(cst.tokens());
+        parser.set_bundle_assets(true);
+        let document = match parser.parse_file(file).and_then(|document| parser.check(&document).map(|_| document)) {
+            Ok(document) => document,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                process::exit(1);
+            }
+        };
+        //a chapter without a title is listed under its file name
+        let chapter_title = document.head.iter().flatten()
+            .find_map(|node| match node {
+                ast::Node::Title(content) => Some(ast::plain_text(content).trim().to_string()),
+                _ => None,
+            })
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| stem(file));
+        compiled.push(epub::Chapter { title: chapter_title, html: html::render(&document, false), assets: parser.assets().files().to_vec() });
+    }
+
+    let title = title.unwrap_or_else(|| stem(out));
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let result = epub::build(&title, &compiled, &epub::timestamp(now))
+        .and_then(|book| fs::write(out, book).map_err(|e| format!("Code Generation Error: Could not write file '{}'. {}", out, e)));
+    match result {
+        Ok(()) => {
+            println!("Successfully compiled {} chapter(s) to '{}'.", compiled.len(), out);
+            process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+ }
 
  fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2{
-        eprintln!("Usage: lolcompiler <input_file.lol>");
-        eprintln!("Error: Compiler requires exactly one command-line argument (the input file name).");
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("fmt") => fmt_command(&args[1..]),
+        Some("import") => convert_command("import", &args[1..], import::markdown_to_lol),
+        Some("decompile") => convert_command("decompile", &args[1..], decompile::html_to_lol),
+        Some("epub") => epub_command(&args[1..]),
+        _ => {}
+    }
+    let mut strict = false;
+    let mut out_dir = None;
+    let mut hash_assets = false;
+    let mut keep_comments = false;
+    let mut allowed_lints = Vec::new();
+    let mut format = Format::Html;
+    let mut width = None;
+    let mut keep_emphasis = false;
+    let mut emit = None;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => strict = true, //warnings become errors
+            "--out-dir" => match args.next() {
+                Some(dir) => out_dir = Some(PathBuf::from(dir)),
+                None => usage_error("--out-dir needs a folder name."),
+            },
+            "--hash-assets" => hash_assets = true,
+            "--keep-comments" => keep_comments = true, //#OBTW comments become <!-- -->
+            "--allow" => match args.next().map(|name| (name, LintId::from_name(name))) {
+                Some((_, Some(id))) => allowed_lints.push(id),
+                Some((name, None)) => {
+                    let names: Vec<&str> = ALL_LINTS.iter().map(|id| id.name()).collect();
+                    usage_error(&format!("Unknown lint '{}'. Lints are: {}.", name, names.join(", ")))
+                }
+                None => usage_error("--allow needs a lint name."),
+            },
+            "--format" => match args.next().map(|name| (name, Format::from_name(name))) {
+                Some((_, Some(f))) => format = f,
+                Some((name, None)) => {
+                    let names: Vec<&str> = ALL_FORMATS.iter().map(|f| f.name()).collect();
+                    usage_error(&format!("Unknown format '{}'. Formats are: {}.", name, names.join(", ")))
+                }
+                None => usage_error("--format needs a format name."),
+            },
+            "--width" => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(columns)) => width = Some(columns),
+                _ => usage_error("--width needs a whole number of columns, 0 turns wrapping off."),
+            },
+            "--keep-emphasis" => keep_emphasis = true,
+            "--emit" => match args.next().map(|name| (name, Emit::from_name(name))) {
+                Some((_, Some(e))) => emit = Some(e),
+                Some((name, None)) => {
+                    let names: Vec<&str> = ALL_EMITS.iter().map(|e| e.name()).collect();
+                    usage_error(&format!("Unknown --emit '{}'. It can be: {}.", name, names.join(", ")))
+                }
+                None => usage_error("--emit needs the name of what to write."),
+            },
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option '{}'.", arg)),
+            _ => files.push(arg),
+        }
+    }
+    if width.is_some() && format != Format::Text {
+        usage_error("--width only works together with --format text.");
+    }
+    if keep_emphasis && format != Format::Gemtext {
+        usage_error("--keep-emphasis only works together with --format gemtext.");
+    }
+    if hash_assets && out_dir.is_none() {
+        usage_error("--hash-assets only works together with --out-dir.");
+    }
+    if files.len() != 1{
+        eprintln!("{}", USAGE);
+        eprintln!("Error: Compiler requires exactly one input file name.");
         process::exit(1);
     }
 
-    let input_filename = &args[1];
+    let input_filename = files[0];
 
-    if !input_filename.ends_with(".lol"){
-        eprintln!("Error: Input file must have the required '.lol' extension.");
+    //a .json file is a document exported with --emit ast-json
+    let from_json = input_filename.ends_with(".json");
+    if from_json && emit.is_some() {
+        usage_error("--emit needs a .lol input file.");
+    }
+    if !input_filename.ends_with(".lol") && !from_json {
+        eprintln!("Error: Input file must have the required '.lol' extension (or be an ast-json '.json' file).");
         eprintln!("Received: {}", input_filename);
         process::exit(1);
     }
@@ -29,18 +316,38 @@ let source_code = match fs::read_to_string(input_filename) {
             process::exit(1);
         }
     };
-    let mut lexer = Lexer::new(&source_code);
-    let tokens = match lexer.lex() {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Lexer Error: Tokenization failed:\n{}", e);
-            process::exit(1);
+    //the token dump is most useful when the lexer fails, so it runs before the error check
+    if let Some(Emit::Tokens | Emit::TokensJsonl) = emit {
+        if emit == Some(Emit::Tokens) {
+            print!("{}", dump::table(&source_code));
+        } else {
+            print!("{}", dump::json_lines(&source_code));
         }
-    };
+        process::exit(if dump::has_errors(&source_code) { 1 } else { 0 });
+    }
+    let cst = if from_json { Cst::parse("") } else { Cst::parse(&source_code) };
+    if let Some(e) = cst.errors.first() {
+        eprintln!("Lexer Error: Tokenization failed:\n{}", e);
+        process::exit(1);
+    }
 
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::new(cst.tokens());
+    parser.set_strict(strict);
+    parser.set_out_dir(out_dir, hash_assets);
+    parser.set_allowed_lints(allowed_lints);
 
-    match parser.compile_and_run(input_filename) {
+    let result = if let Some(Emit::AstJson) = emit {
+        parser.parse_file(input_filename).map(|document| print!("{}", ast_json::export(&document, &cst)))
+    } else {
+        let document = if from_json { ast_json::import(&source_code) } else { parser.parse_file(input_filename) };
+        document.and_then(|document| {
+            parser.check(&document)?;
+            let options = RenderOptions { keep_comments, width: width.unwrap_or(text::DEFAULT_WIDTH), keep_emphasis };
+            let output = output::render(&document, format, &options);
+            parser.write_output(&output, format, input_filename)
+        })
+    };
+    match result {
         Ok(_) => {
 
         },
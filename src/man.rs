@@ -0,0 +1,226 @@
+//roff generation for man pages: the head title goes into .TH, paragraphs are .PP, list items
+//are .IP bullets and media players are written out as their address, a terminal cannot play them
+use crate::ast::{self, Document, Node};
+
+/// the manual section the page is written for, user commands
+pub const SECTION: &str = "1";
+
+/// renders a parsed document as a man page
+pub fn render(document: &Document, keep_comments: bool) -> String {
+    let mut renderer = ManRenderer { blocks: Vec::new(), current: String::new(), keep_comments };
+    renderer.render_nodes(&document.prologue);
+    if let Some(head) = &document.head {
+        renderer.render_nodes(head);
+    }
+    renderer.render_nodes(&document.body);
+    renderer.end_paragraph();
+
+    let title = document.head.iter().flatten()
+        .filter_map(|node| match node {
+            Node::Title(content) => Some(ast::plain_text(content).split_whitespace().collect::<Vec<_>>().join(" ")),
+            _ => None,
+        })
+        .find(|title| !title.is_empty());
+
+    let mut output = String::new();
+    //man pages name themselves in capitals
+    if let Some(title) = title {
+        output.push_str(&format!(".TH \"{}\" {}\n", escape_text(&title.to_uppercase(), false).replace('"', "\\(dq"), SECTION));
+    }
+    for block in &renderer.blocks {
+        output.push_str(block);
+        output.push('\n');
+    }
+    output
+}
+
+struct ManRenderer {
+    blocks: Vec<String>, // finished blocks, each starts with its macro line
+    current: String,     // the paragraph being written, control lines on lines of their own
+    keep_comments: bool,
+}
+
+impl ManRenderer {
+    fn render_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.render_node(node);
+        }
+    }
+
+    fn render_node(&mut self, node: &Node) {
+        match node {
+            //the title goes into .TH, see render
+            Node::Title(_) => {}
+            Node::Paragraph(content) => {
+                self.end_paragraph();
+                self.render_nodes(content);
+                self.end_paragraph();
+            }
+            Node::List(_) | Node::Item(_) => {
+                self.end_paragraph();
+                let mut lines = Vec::new();
+                self.list(std::slice::from_ref(node), &mut lines);
+                if !lines.is_empty() {
+                    self.blocks.push(lines.join("\n"));
+                }
+            }
+            Node::Audio(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!(".PP\nAudio: {}", escape_text(&m.address.text, false)));
+            }
+            Node::Video(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!(".PP\nVideo: {}", escape_text(&m.address.text, false)));
+            }
+            //a variable's markup is rendered again at every use
+            Node::Variable { value, .. } => self.render_nodes(value),
+            Node::Comment(text) if self.keep_comments && self.current.trim().is_empty() => {
+                self.end_paragraph();
+                self.blocks.push(comment(text));
+            }
+            _ => inline(&mut self.current, std::slice::from_ref(node), self.keep_comments),
+        }
+    }
+
+    //roff fills the text itself, so lines are only broken where a control line goes. a .br
+    //with no text before or after it breaks nothing and is left out
+    fn end_paragraph(&mut self) {
+        let paragraph = std::mem::take(&mut self.current);
+        let mut lines: Vec<&str> = paragraph.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        while lines.first() == Some(&".br") {
+            lines.remove(0);
+        }
+        while lines.last() == Some(&".br") {
+            lines.pop();
+        }
+        if !lines.is_empty() {
+            self.blocks.push(format!(".PP\n{}", lines.join("\n")));
+        }
+    }
+
+    //nested lists are indented with .RS/.RE
+    fn list(&self, content: &[Node], lines: &mut Vec<String>) {
+        for node in content {
+            match node {
+                Node::List(items) if lines.is_empty() => self.list(items, lines),
+                Node::List(items) => {
+                    lines.push(".RS".to_string());
+                    self.list(items, lines);
+                    lines.push(".RE".to_string());
+                }
+                Node::Item(item) => {
+                    lines.push(".IP \\(bu 2".to_string());
+                    let mut text = String::new();
+                    inline(&mut text, item, self.keep_comments);
+                    lines.extend(text.lines().map(str::trim).filter(|line| !line.is_empty() && *line != ".br").map(str::to_string));
+                }
+                Node::Comment(text) if self.keep_comments => lines.push(comment(text)),
+                _ => {}
+            }
+        }
+    }
+}
+
+//inline content, text is joined into one line and breaks and comments get lines of their own
+fn inline(output: &mut String, nodes: &[Node], keep_comments: bool) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let line = &output[output.rfind('\n').map_or(0, |i| i + 1)..];
+                output.push_str(&escape_text(text, line.trim().is_empty()));
+            }
+            Node::Bold(content) => font(output, "B", content, keep_comments),
+            Node::Italics(content) => font(output, "I", content, keep_comments),
+            Node::Variable { value, .. } => inline(output, value, keep_comments),
+            Node::Newline => output.push_str("\n.br\n"),
+            Node::Comment(text) if keep_comments => {
+                output.push('\n');
+                output.push_str(&comment(text));
+                output.push('\n');
+            }
+            _ => {}
+        }
+    }
+}
+
+//\fB or \fI before the content and back to the regular font after it, spaces at either end
+//stay outside
+fn font(output: &mut String, name: &str, content: &[Node], keep_comments: bool) {
+    let mut inner = String::new();
+    inline(&mut inner, content, keep_comments);
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        output.push_str(&inner);
+        return;
+    }
+    let start = inner.len() - inner.trim_start().len();
+    output.push_str(&inner[..start]);
+    output.push_str(&format!("\\f{}{}\\fR", name, trimmed));
+    output.push_str(&inner[start + trimmed.len()..]);
+}
+
+//.\" before every line of the comment
+fn comment(text: &str) -> String {
+    text.trim().lines().map(|line| format!(".\\\" {}", line.trim())).collect::<Vec<_>>().join("\n")
+}
+
+/// text with backslashes escaped and line breaks turned into spaces. when the text starts a
+/// line, a . or ' at its start, which roff would read as a request, gets \& in front of it
+pub fn escape_text(text: &str, line_start: bool) -> String {
+    let text = text.replace(['\n', '\r'], " ");
+    let mut escaped = String::with_capacity(text.len());
+    let mut line_start = line_start;
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\e"),
+            '.' | '\'' if line_start => {
+                escaped.push_str("\\&");
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+        line_start = c == ' ' && line_start;
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_text(".profile and 'quotes' in C:\\Users.", true), "\\&.profile and 'quotes' in C:\\eUsers.");
+        assert_eq!(escape_text("  'x", true), "  \\&'x");
+        assert_eq!(escape_text(" .", false), " .");
+    }
+
+    #[test]
+    fn test_render_list_breaks_and_comments() {
+        let source = "#HAI #MAEK HEAD #GIMMEH TITLE lol \"c\" #MKAY #OIC #MAEK PARAGRAF #GIMMEH NEWLINE one #GIMMEH BOLD two #MKAY #GIMMEH NEWLINE .three #GIMMEH NEWLINE #OIC
+            #MAEK LIST #GIMMEH ITEM #GIMMEH ITALICS fish #MKAY & chips #MKAY #OBTW more
+            later #TLDR #OIC #GIMMEH SOUNDZ theme.mp3 #MKAY #KTHXBYE";
+        let document = testing::parse(source);
+
+        assert_eq!(render(&document, true), "\
+.TH \"LOL \\(dqC\\(dq\" 1
+.PP
+one \\fBtwo\\fR
+.br
+\\&.three
+.IP \\(bu 2
+\\fIfish\\fR & chips
+.\\\" more
+.\\\" later
+.PP
+Audio: theme.mp3
+");
+    }
+
+    //tests/TestN.1 is the expected output for tests/TestN.lol, comments left out
+    #[test]
+    fn test_golden_files() {
+        testing::check_golden_files(SECTION, |document| render(document, false));
+    }
+}
@@ -0,0 +1,269 @@
+//markdown (commonmark) generation from the same document tree the html renderer uses.
+//the title becomes a level 1 heading, #GIMMEH NEWLINE a hard line break and media players
+//are kept as html blocks, which commonmark passes through unchanged
+use crate::ast::{self, Document, Node};
+use crate::html;
+use crate::media;
+
+/// renders a parsed document as a markdown file
+pub fn render(document: &Document, keep_comments: bool) -> String {
+    let mut renderer = MarkdownRenderer { blocks: Vec::new(), current: String::new(), keep_comments };
+    renderer.render_nodes(&document.prologue);
+    if let Some(head) = &document.head {
+        renderer.render_nodes(head);
+    }
+    renderer.render_nodes(&document.body);
+    renderer.end_paragraph();
+
+    let mut output = renderer.blocks.join("\n\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+struct MarkdownRenderer {
+    blocks: Vec<String>, // finished blocks, separated by a blank line in the output
+    current: String,     // the paragraph being written
+    keep_comments: bool,
+}
+
+impl MarkdownRenderer {
+    fn render_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.render_node(node);
+        }
+    }
+
+    fn render_node(&mut self, node: &Node) {
+        match node {
+            Node::Title(content) => {
+                //a closing run of # would be read as part of the heading syntax
+                let title = escape_text(ast::plain_text(content).trim(), false).replace('#', "\\#");
+                if !title.is_empty() {
+                    self.end_paragraph();
+                    self.blocks.push(format!("# {}", title));
+                }
+            }
+            Node::Paragraph(content) => {
+                self.end_paragraph();
+                self.render_nodes(content);
+                self.end_paragraph();
+            }
+            Node::List(_) | Node::Item(_) => {
+                self.end_paragraph();
+                let list = self.list(std::slice::from_ref(node));
+                if !list.is_empty() {
+                    self.blocks.push(list);
+                }
+            }
+            Node::Audio(m) => self.html_block(&media::render_audio(&m.address, &m.options)),
+            Node::Video(m) => self.html_block(&media::render_video(&m.address, &m.options)),
+            //a variable's markup is rendered again at every use
+            Node::Variable { value, .. } => self.render_nodes(value),
+            //a break at the start of a paragraph has nothing to end
+            Node::Newline if self.current.is_empty() => {}
+            Node::Comment(text) if self.keep_comments && self.current.is_empty() => {
+                self.end_paragraph();
+                self.blocks.push(html::html_comment(text));
+            }
+            _ => inline(&mut self.current, std::slice::from_ref(node), self.keep_comments),
+        }
+    }
+
+    //a line break at the very end of a paragraph is not a hard break in markdown, so it is
+    //kept as html to give the same output
+    fn end_paragraph(&mut self) {
+        let mut paragraph = std::mem::take(&mut self.current);
+        if let Some(stripped) = paragraph.strip_suffix("\\\n") {
+            paragraph = format!("{}<br>", stripped);
+        }
+        let paragraph = paragraph.trim();
+        if !paragraph.is_empty() {
+            self.blocks.push(paragraph.to_string());
+        }
+    }
+
+    //<audio> and <video> do not start an html block on their own, a <div> around them does
+    fn html_block(&mut self, html: &str) {
+        self.end_paragraph();
+        self.blocks.push(format!("<div>\n{}\n</div>", html.trim_end()));
+    }
+
+    //one "- " line per item, comments between items go inline into the next item
+    fn list(&self, content: &[Node]) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut comments = String::new();
+        for node in content {
+            match node {
+                Node::List(items) => {
+                    let list = self.list(items);
+                    if !list.is_empty() {
+                        lines.push(list);
+                    }
+                }
+                Node::Item(item) => {
+                    let mut text = std::mem::take(&mut comments);
+                    inline(&mut text, item, self.keep_comments);
+                    //continuation lines after a hard break belong to the item
+                    let text = text.trim().replace('\n', "\n  ");
+                    lines.push(if text.is_empty() { "-".to_string() } else { format!("- {}", text) });
+                }
+                Node::Comment(text) if self.keep_comments => {
+                    comments.push_str(&html::html_comment(text));
+                    comments.push(' ');
+                }
+                _ => {}
+            }
+        }
+        if let Some(last) = lines.last_mut() {
+            if !comments.is_empty() {
+                last.push(' ');
+                last.push_str(comments.trim_end());
+            }
+        } else if !comments.is_empty() {
+            lines.push(comments.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+//inline content: text, emphasis, breaks and comments. blocks never appear inside a span or
+//a list item, the parser does not allow them there
+fn inline(output: &mut String, nodes: &[Node], keep_comments: bool) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let line_start = output.is_empty() || output.ends_with('\n');
+                output.push_str(&escape_text(text, line_start));
+            }
+            Node::Bold(content) => emphasis(output, "**", content, keep_comments),
+            Node::Italics(content) => emphasis(output, "*", content, keep_comments),
+            Node::Variable { value, .. } => inline(output, value, keep_comments),
+            Node::Newline => output.push_str("\\\n"),
+            Node::Comment(text) if keep_comments => output.push_str(&html::html_comment(text)),
+            _ => {}
+        }
+    }
+}
+
+//empty emphasis is dropped, "****" would be read as text
+fn emphasis(output: &mut String, marker: &str, content: &[Node], keep_comments: bool) {
+    let start = output.len();
+    output.push_str(marker);
+    inline(output, content, keep_comments);
+    if output.len() == start + marker.len() {
+        output.truncate(start);
+    } else {
+        output.push_str(marker);
+    }
+}
+
+/// text with the characters markdown would read as markup escaped. at the start of a line
+/// the markers of headings, quotes, lists and thematic breaks are escaped as well
+pub fn escape_text(text: &str, line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' => escaped.push('\\'),
+            //only & that could start an entity like &amp; needs it
+            '&' if chars.peek().is_some_and(|n| n.is_ascii_alphanumeric() || *n == '#') => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    if !line_start {
+        return escaped;
+    }
+
+    let indent = escaped.len() - escaped.trim_start().len();
+    let rest = &escaped[indent..];
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if rest.starts_with(['#', '>', '-', '+', '=']) {
+        escaped.insert(indent, '\\');
+    } else if digits > 0 && rest[digits..].starts_with(['.', ')']) {
+        //"1. " would start an ordered list
+        escaped.insert(indent + digits, '\\');
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_render_blocks_and_inline_markup() {
+        let document = testing::parse("#HAI #MAEK HEAD #GIMMEH TITLE Fish & Chips #MKAY #OIC
+            #MAEK PARAGRAF #GIMMEH ITALICS What is *this*? #MKAY The meaning of #GIMMEH BOLD life #MKAY #GIMMEH NEWLINE
+            - not a list #GIMMEH NEWLINE #OIC
+            #MAEK LIST #GIMMEH ITEM one #MKAY #GIMMEH ITEM #GIMMEH BOLD two #MKAY #MKAY #OIC
+            1. done #GIMMEH NEWLINE #GIMMEH SOUNDZ theme.mp3 #MKAY #KTHXBYE");
+
+        let expected = "# Fish & Chips
+
+*What is \\*this\\*?* The meaning of **life**\\
+\\- not a list<br>
+
+- one
+- **two**
+
+1\\. done<br>
+
+<div>
+<audio controls><source src=\"theme.mp3\" type=\"audio/mpeg\"></audio>
+</div>
+";
+        assert_eq!(render(&document, false), expected);
+    }
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("a_b [c] <d> &amp; & e", false), "a\\_b \\[c\\] \\<d> \\&amp; & e");
+        assert_eq!(escape_text("# not a heading", true), "\\# not a heading");
+        assert_eq!(escape_text("# fine", false), "# fine");
+        assert_eq!(escape_text("2) two", true), "2\\) two");
+    }
+
+    //html tags as markdown spells them
+    const MARKUP: [(&str, &str); 7] = [("<title>", "# "), ("<li>", "- "), ("<b>", "**"), ("</b>", "**"), ("<i>", "*"), ("</i>", "*"), ("<br>", "\\")];
+
+    //the words of a markdown page, escapes undone and raw html left out like testing::html_words does
+    fn markdown_words(markdown: &str) -> Vec<String> {
+        let markdown = markdown.replace("<br>", "\\\\");
+        let mut text = String::new();
+        let mut chars = markdown.chars().peekable();
+        let mut in_tag = false;
+        while let Some(c) = chars.next() {
+            match chars.peek() {
+                _ if in_tag => in_tag = c != '>',
+                Some(&next) if c == '\\' && next.is_ascii_punctuation() => {
+                    text.push(next);
+                    chars.next();
+                }
+                Some(&next) if c == '<' && (next.is_ascii_alphabetic() || next == '/' || next == '!') => {
+                    in_tag = true;
+                    text.push(' ');
+                }
+                _ => text.push(c),
+            }
+        }
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_every_test_file_renders_the_same_content_as_html() {
+        for file in testing::test_files() {
+            for keep_comments in [false, true] {
+                assert_eq!(
+                    markdown_words(&render(&file.document, keep_comments)),
+                    testing::html_words(&html::render(&file.document, keep_comments), &MARKUP),
+                    "{}",
+                    file.path.display()
+                );
+            }
+        }
+    }
+}
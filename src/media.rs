@@ -0,0 +1,417 @@
+//media helpers for SOUNDZ and VIDZ, options parsing and html generation
+use crate::address::Address;
+use crate::html::escape_attribute;
+
+/// optional settings written after a SOUNDZ/VIDZ address
+/// e.g. `#GIMMEH VIDZ clip.mp4 autoplay muted width=640 start=30 #MKAY`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MediaOptions {
+    pub autoplay: bool,
+    pub looped: bool,
+    pub muted: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub start: Option<u32>,
+    pub captions: Option<String>,
+}
+
+impl MediaOptions {
+    /// parses whitespace separated flags (autoplay, loop, muted) and key=value pairs
+    /// (width, height, start, captions)
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut options = MediaOptions::default();
+
+        for option in text.split_whitespace() {
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key.to_lowercase(), Some(value)),
+                None => (option.to_lowercase(), None),
+            };
+
+            match (key.as_str(), value) {
+                ("autoplay", None) => options.autoplay = true,
+                ("loop", None) => options.looped = true,
+                ("muted", None) => options.muted = true,
+                ("width", Some(v)) => options.width = Some(parse_number(&key, v)?),
+                ("height", Some(v)) => options.height = Some(parse_number(&key, v)?),
+                ("start", Some(v)) => options.start = Some(parse_start(v)?),
+                ("captions", Some(v)) if !v.is_empty() => options.captions = Some(v.to_string()),
+                ("autoplay" | "loop" | "muted", Some(_)) => {
+                    return Err(format!("Syntax Error: Media option '{}' is a flag and does not take a value.", key));
+                }
+                ("width" | "height" | "start" | "captions", _) => {
+                    return Err(format!("Syntax Error: Media option '{}' needs a value, e.g. {}=...", key, key));
+                }
+                _ => return Err(format!("Syntax Error: Unknown media option '{}'.", option)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// true when a word is one of the options above, e.g. autoplay or width=640
+pub fn is_option(word: &str) -> bool {
+    let key = word.split('=').next().unwrap_or(word).to_lowercase();
+    matches!(key.as_str(), "autoplay" | "loop" | "muted" | "width" | "height" | "start" | "captions")
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u32, String> {
+    value.parse::<u32>()
+        .map_err(|_| format!("Syntax Error: Media option '{}' must be a whole number, found '{}'.", key, value))
+}
+
+//start time is either plain seconds (90) or minutes:seconds (1:30)
+fn parse_start(value: &str) -> Result<u32, String> {
+    let error = || format!("Syntax Error: Media option 'start' must be seconds or m:ss, found '{}'.", value);
+    match value.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes = minutes.parse::<u32>().map_err(|_| error())?;
+            let seconds = seconds.parse::<u32>().map_err(|_| error())?;
+            if seconds >= 60 {
+                return Err(error());
+            }
+            minutes.checked_mul(60).and_then(|m| m.checked_add(seconds)).ok_or_else(error)
+        }
+        None => value.parse::<u32>().map_err(|_| error()),
+    }
+}
+
+/// file extension of an address, ignoring any query string or fragment
+pub fn extension(src: &str) -> Option<String> {
+    let path = src.split(['?', '#']).next().unwrap_or(src);
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    file_name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase())
+}
+
+/// mime type for the <source> tag, None lets the browser sniff it
+pub fn audio_mime_type(src: &str) -> Option<&'static str> {
+    match extension(src)?.as_str() {
+        "mp3" => Some("audio/mpeg"),
+        "ogg" | "oga" | "opus" => Some("audio/ogg"),
+        "wav" => Some("audio/wav"),
+        "m4a" => Some("audio/mp4"),
+        "aac" => Some("audio/aac"),
+        "flac" => Some("audio/flac"),
+        "weba" => Some("audio/webm"),
+        _ => None,
+    }
+}
+
+/// options that make no sense for audio are reported while parsing
+pub fn check_audio(options: &MediaOptions) -> Result<(), String> {
+    if options.width.is_some() || options.height.is_some() {
+        return Err("Syntax Error: width/height are not supported on #GIMMEH SOUNDZ.".to_string());
+    }
+    Ok(())
+}
+
+pub fn render_audio(address: &Address, options: &MediaOptions) -> String {
+    let mut html = String::from("<audio controls");
+    push_playback_flags(&mut html, options);
+    html.push('>');
+
+    //media fragment makes the browser seek before playing
+    let src = match options.start {
+        Some(start) => with_start_time(&address.text, start),
+        None => address.text.clone(),
+    };
+    match audio_mime_type(&src) {
+        Some(mime) => html.push_str(&format!("<source src=\"{}\" type=\"{}\">", escape_attribute(&src), mime)),
+        None => html.push_str(&format!("<source src=\"{}\">", escape_attribute(&src))),
+    }
+    if let Some(captions) = &options.captions {
+        html.push_str(&format!("<track kind=\"captions\" src=\"{}\" default>", escape_attribute(captions)));
+    }
+    html.push_str("</audio>\n");
+    html
+}
+
+/// mime type for a video file address, None when the address is not a media file
+pub fn video_mime_type(src: &str) -> Option<&'static str> {
+    match extension(src)?.as_str() {
+        "mp4" | "m4v" => Some("video/mp4"),
+        "webm" => Some("video/webm"),
+        "ogv" | "ogg" => Some("video/ogg"),
+        "mov" => Some("video/quicktime"),
+        _ => None,
+    }
+}
+
+/// video hosts that we know how to embed
+#[derive(Debug, PartialEq)]
+pub enum VideoProvider {
+    YouTube(String),
+    Vimeo(String),
+}
+
+fn query_value<'q>(query: &'q str, key: &str) -> Option<&'q str> {
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+//keeps only characters that can appear in a provider's video id
+fn clean_id(id: &str) -> Option<String> {
+    let id: String = id.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_').collect();
+    if id.is_empty() { None } else { Some(id) }
+}
+
+/// recognises YouTube watch/short/embed links and Vimeo links
+pub fn video_provider(address: &Address) -> Option<VideoProvider> {
+    let host = address.host.as_deref()?;
+    let query = address.query.as_deref().unwrap_or("");
+    let segments: Vec<&str> = address.path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match host.strip_prefix("www.").unwrap_or(host) {
+        "youtube.com" | "m.youtube.com" | "youtube-nocookie.com" => {
+            let id = match segments.as_slice() {
+                ["watch"] => query_value(query, "v")?,
+                ["shorts" | "embed" | "live" | "v", id, ..] => id,
+                _ => return None,
+            };
+            clean_id(id).map(VideoProvider::YouTube)
+        }
+        "youtu.be" => clean_id(segments.first()?).map(VideoProvider::YouTube),
+        "vimeo.com" | "player.vimeo.com" => {
+            //vimeo.com/123, vimeo.com/channels/staff/123, player.vimeo.com/video/123
+            let id = segments.iter().rev().find(|s| s.chars().all(|c| c.is_ascii_digit()))?;
+            Some(VideoProvider::Vimeo(id.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// reports VIDZ problems while parsing: captions need a media file, and an address that is
+/// neither a media file nor a known host only gets a warning
+pub fn check_video(address: &Address, options: &MediaOptions, warnings: &mut Vec<String>) -> Result<(), String> {
+    if video_mime_type(&address.text).is_some() {
+        return Ok(());
+    }
+    if options.captions.is_some() {
+        return Err("Syntax Error: captions= cannot be attached to an embedded (iframe) video.".to_string());
+    }
+    if video_provider(address).is_none() {
+        warnings.push(format!("Warning: Video address '{}' is not a media file or a known video host (YouTube, Vimeo); it is embedded as given and may refuse to play in a frame.", address.text));
+    }
+    Ok(())
+}
+
+/// renders a VIDZ address: files get a native <video>, known providers are rewritten to their
+/// embed address and anything else is framed as given
+pub fn render_video(address: &Address, options: &MediaOptions) -> String {
+    let src = address.text.as_str();
+    if let Some(mime) = video_mime_type(src) {
+        return render_native_video(src, mime, options);
+    }
+
+    let (embed_src, params) = match video_provider(address) {
+        Some(VideoProvider::YouTube(id)) => {
+            let mut params = embed_params(options, "mute");
+            //youtube only loops a single video when it is also its own playlist
+            if options.looped {
+                params.push(format!("playlist={}", id));
+            }
+            (format!("https://www.youtube.com/embed/{}", id), params)
+        }
+        Some(VideoProvider::Vimeo(id)) => {
+            let mut embed = format!("https://player.vimeo.com/video/{}", id);
+            //vimeo takes the start time as a fragment rather than a parameter
+            if let Some(start) = options.start {
+                embed.push_str(&format!("#t={}s", start));
+            }
+            let options = MediaOptions { start: None, ..options.clone() };
+            (embed, embed_params(&options, "muted"))
+        }
+        None => (src.to_string(), embed_params(options, "mute")),
+    };
+    let embed_src = append_query(&embed_src, &params);
+
+    let mut html = format!("<iframe src=\"{}\"", escape_attribute(&embed_src));
+    push_dimensions(&mut html, options);
+    if options.autoplay {
+        html.push_str(" allow=\"autoplay\"");
+    }
+    html.push_str(" frameborder=\"0\" allowfullscreen></iframe>\n");
+    html
+}
+
+fn render_native_video(src: &str, mime: &str, options: &MediaOptions) -> String {
+    let mut html = String::from("<video controls");
+    push_playback_flags(&mut html, options);
+    push_dimensions(&mut html, options);
+    html.push('>');
+
+    let src = match options.start {
+        Some(start) => with_start_time(src, start),
+        None => src.to_string(),
+    };
+    html.push_str(&format!("<source src=\"{}\" type=\"{}\">", escape_attribute(&src), mime));
+    if let Some(captions) = &options.captions {
+        html.push_str(&format!("<track kind=\"captions\" src=\"{}\" default>", escape_attribute(captions)));
+    }
+    html.push_str("</video>\n");
+    html
+}
+
+//embedded players take playback settings as query parameters
+fn embed_params(options: &MediaOptions, mute_key: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    if options.autoplay {
+        params.push("autoplay=1".to_string());
+    }
+    if options.looped {
+        params.push("loop=1".to_string());
+    }
+    if options.muted {
+        params.push(format!("{}=1", mute_key));
+    }
+    if let Some(start) = options.start {
+        params.push(format!("start={}", start));
+    }
+    params
+}
+
+fn push_playback_flags(html: &mut String, options: &MediaOptions) {
+    if options.autoplay {
+        html.push_str(" autoplay");
+    }
+    if options.looped {
+        html.push_str(" loop");
+    }
+    if options.muted {
+        html.push_str(" muted");
+    }
+}
+
+fn push_dimensions(html: &mut String, options: &MediaOptions) {
+    if let Some(width) = options.width {
+        html.push_str(&format!(" width=\"{}\"", width));
+    }
+    if let Some(height) = options.height {
+        html.push_str(&format!(" height=\"{}\"", height));
+    }
+}
+
+//the start time as a t= media fragment, joined with & to a fragment the address already has.
+//start= wins over a t= written in the address
+fn with_start_time(src: &str, start: u32) -> String {
+    let (base, fragment) = src.split_once('#').unwrap_or((src, ""));
+    let time = format!("t={}", start);
+    let mut parts: Vec<&str> = fragment.split('&').filter(|part| !part.is_empty() && !part.starts_with("t=")).collect();
+    parts.push(&time);
+    format!("{}#{}", base, parts.join("&"))
+}
+
+//adds query parameters before any #fragment, keeping ones already in the address
+fn append_query(src: &str, params: &[String]) -> String {
+    if params.is_empty() {
+        return src.to_string();
+    }
+    let (base, fragment) = match src.split_once('#') {
+        Some((base, fragment)) => (base, Some(fragment)),
+        None => (src, None),
+    };
+    let separator = if base.contains('?') { '&' } else { '?' };
+    let mut result = format!("{}{}{}", base, separator, params.join("&"));
+    if let Some(fragment) = fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        let options = MediaOptions::parse("autoplay LOOP width=640 height=360 start=1:30 captions=subs.vtt").unwrap();
+
+        assert!(options.autoplay);
+        assert!(options.looped);
+        assert!(!options.muted);
+        assert_eq!(options.width, Some(640));
+        assert_eq!(options.height, Some(360));
+        assert_eq!(options.start, Some(90));
+        assert_eq!(options.captions, Some("subs.vtt".to_string()));
+    }
+
+    #[test]
+    fn test_parse_options_errors() {
+        assert!(MediaOptions::parse("fullscreen").is_err());
+        assert!(MediaOptions::parse("width=wide").is_err());
+        assert!(MediaOptions::parse("autoplay=yes").is_err());
+        assert!(MediaOptions::parse("captions").is_err());
+        assert!(MediaOptions::parse("start=99999999:00").unwrap_err().contains("must be seconds or m:ss"));
+        assert!(MediaOptions::parse("start=99999999999:00").unwrap_err().contains("must be seconds or m:ss"));
+    }
+
+    #[test]
+    fn test_audio_mime_type() {
+        assert_eq!(audio_mime_type("song.OGG"), Some("audio/ogg"));
+        assert_eq!(audio_mime_type("http://example.com/a.wav?x=1"), Some("audio/wav"));
+        assert_eq!(audio_mime_type("clips/voice.m4a"), Some("audio/mp4"));
+        assert_eq!(audio_mime_type("http://example.com/stream"), None);
+    }
+
+    fn address(text: &str) -> Address {
+        Address::parse(text, &mut Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn test_render_audio() {
+        let options = MediaOptions::parse("loop muted start=5").unwrap();
+        assert_eq!(
+            render_audio(&address("theme.mp3"), &options),
+            "<audio controls loop muted><source src=\"theme.mp3#t=5\" type=\"audio/mpeg\"></audio>\n"
+        );
+        //a fragment written in the address is kept next to the start time
+        assert!(render_audio(&address("theme.mp3#x"), &options).contains("src=\"theme.mp3#x&amp;t=5\""));
+        assert!(render_audio(&address("theme.mp3#t=9&x"), &options).contains("src=\"theme.mp3#x&amp;t=5\""));
+        assert!(render_video(&address("clip.mp4#x"), &options).contains("src=\"clip.mp4#x&amp;t=5\""));
+    }
+
+    #[test]
+    fn test_render_video_options() {
+        let options = MediaOptions::parse("autoplay width=640").unwrap();
+        let mut warnings = Vec::new();
+        check_video(&address("https://example.com/embed/1?a=b"), &options, &mut warnings).unwrap();
+        assert_eq!(
+            render_video(&address("https://example.com/embed/1?a=b"), &options),
+            "<iframe src=\"https://example.com/embed/1?a=b&amp;autoplay=1\" width=\"640\" allow=\"autoplay\" frameborder=\"0\" allowfullscreen></iframe>\n"
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_video_provider() {
+        let youtube = Some(VideoProvider::YouTube("zoO0s1ukcqQ".to_string()));
+        assert_eq!(video_provider(&address("https://www.youtube.com/watch?v=zoO0s1ukcqQ&t=10")), youtube);
+        assert_eq!(video_provider(&address("https://youtu.be/zoO0s1ukcqQ?si=abc")), youtube);
+        assert_eq!(video_provider(&address("https://youtube.com/shorts/zoO0s1ukcqQ")), youtube);
+        assert_eq!(video_provider(&address("http://www.youtube.com/embed/zoO0s1ukcqQ")), youtube);
+        assert_eq!(video_provider(&address("https://vimeo.com/76979871")), Some(VideoProvider::Vimeo("76979871".to_string())));
+        assert_eq!(video_provider(&address("https://tinyurl.com/cosc455dehlinger")), None);
+        assert_eq!(video_provider(&address("clips/intro.mp4")), None);
+    }
+
+    #[test]
+    fn test_render_native_and_provider_video() {
+        let mut warnings = Vec::new();
+        let options = MediaOptions::parse("muted loop captions=intro.vtt").unwrap();
+        check_video(&address("clips/intro.mp4"), &options, &mut warnings).unwrap();
+        assert_eq!(
+            render_video(&address("clips/intro.mp4"), &options),
+            "<video controls loop muted><source src=\"clips/intro.mp4\" type=\"video/mp4\"><track kind=\"captions\" src=\"intro.vtt\" default></video>\n"
+        );
+
+        let options = MediaOptions::parse("loop start=30").unwrap();
+        check_video(&address("https://www.youtube.com/watch?v=zoO0s1ukcqQ"), &options, &mut warnings).unwrap();
+        assert_eq!(
+            render_video(&address("https://www.youtube.com/watch?v=zoO0s1ukcqQ"), &options),
+            "<iframe src=\"https://www.youtube.com/embed/zoO0s1ukcqQ?loop=1&amp;start=30&amp;playlist=zoO0s1ukcqQ\" frameborder=\"0\" allowfullscreen></iframe>\n"
+        );
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//the output formats a document can be rendered to, chosen with --format <name>
+use crate::ast::Document;
+use crate::gemtext;
+use crate::html;
+use crate::latex;
+use crate::man;
+use crate::markdown;
+use crate::text;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Html,
+    Markdown,
+    Text,
+    Latex,
+    Gemtext,
+    Man,
+}
+
+pub const ALL_FORMATS: [Format; 6] = [Format::Html, Format::Markdown, Format::Text, Format::Latex, Format::Gemtext, Format::Man];
+
+impl Format {
+    /// the name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Markdown => "markdown",
+            Format::Text => "text",
+            Format::Latex => "latex",
+            Format::Gemtext => "gemtext",
+            Format::Man => "man",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        ALL_FORMATS.iter().copied().find(|format| format.name() == name.to_lowercase())
+    }
+
+    /// extension of the output file, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Markdown => "md",
+            Format::Text => "txt",
+            Format::Latex => "tex",
+            Format::Gemtext => "gmi",
+            Format::Man => man::SECTION,
+        }
+    }
+}
+
+/// settings for the backends, each one reads only those it has a use for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    pub keep_comments: bool, // #OBTW comments are written as comments of the format, html, markdown, latex and man
+    pub width: usize,        // plain text is wrapped at this many columns, 0 turns wrapping off
+    pub keep_emphasis: bool, // gemtext keeps ** and * around bold and italics
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { keep_comments: false, width: text::DEFAULT_WIDTH, keep_emphasis: false }
+    }
+}
+
+/// renders a parsed document in the given format
+pub fn render(document: &Document, format: Format, options: &RenderOptions) -> String {
+    match format {
+        Format::Html => html::render(document, options.keep_comments),
+        Format::Markdown => markdown::render(document, options.keep_comments),
+        Format::Text => text::render(document, options.width),
+        Format::Latex => latex::render(document, options.keep_comments),
+        Format::Gemtext => gemtext::render(document, options.keep_emphasis),
+        Format::Man => man::render(document, options.keep_comments),
+    }
+}
+
+/// what --emit writes to standard output instead of compiling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    AstJson,
+    Tokens,
+    TokensJsonl,
+}
+
+pub const ALL_EMITS: [Emit; 3] = [Emit::AstJson, Emit::Tokens, Emit::TokensJsonl];
+
+impl Emit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Emit::AstJson => "ast-json",
+            Emit::Tokens => "tokens",
+            Emit::TokensJsonl => "tokens-jsonl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Emit> {
+        ALL_EMITS.iter().copied().find(|emit| emit.name() == name.to_lowercase())
+    }
+}
@@ -1,37 +1,55 @@
 use crate::lexer::Token;
-use std::collections::HashMap;
-use std::fs::File;
+use crate::address::Address;
+use crate::assets::Assets;
+use crate::ast::{self, Document, Media, Node};
+use crate::html;
+use crate::output::Format;
+use crate::lint::{self, LintId};
+use crate::media::{self, MediaOptions};
+use crate::scope::Scopes;
+use crate::suggest;
+use crate::whitespace;
+use std::fs::{self, File};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 //imports
 
 
+#[allow(dead_code)]
 pub trait SyntaxAnalyzer {
-    fn parse_lolcode(&mut self) -> Result<(), String>;
-    fn parse_head(&mut self) -> Result<(), String>;
-    fn parse_title(&mut self) -> Result<(), String>;
+    fn parse_lolcode(&mut self) -> Result<Document, String>;
+    fn parse_head(&mut self) -> Result<Option<Vec<Node>>, String>;
+    fn parse_title(&mut self) -> Result<Node, String>;
     fn parse_comment(&mut self) -> Result<(), String>;
-    fn parse_body(&mut self) -> Result<(), String>;
-    fn parse_paragraph(&mut self) -> Result<(), String>;
-    fn parse_inner_paragraph(&mut self) -> Result<(), String>;
-    fn parse_inner_text(&mut self) -> Result<(), String>;
-    fn parse_variable_define(&mut self) -> Result<(), String>;
-    fn parse_variable_use(&mut self) -> Result<(), String>;
-    fn parse_bold(&mut self) -> Result<(), String>;
-    fn parse_italics(&mut self) -> Result<(), String>;
-    fn parse_list(&mut self) -> Result<(), String>;
-    fn parse_list_items(&mut self) -> Result<(), String>;
-    fn parse_inner_list(&mut self) -> Result<(), String>;
-    fn parse_audio(&mut self) -> Result<(), String>;
-    fn parse_video(&mut self) -> Result<(), String>;
-    fn parse_newline(&mut self) -> Result<(), String>;
+    fn parse_body(&mut self) -> Result<Vec<Node>, String>;
+    fn parse_paragraph(&mut self) -> Result<Node, String>;
+    fn parse_inner_paragraph(&mut self) -> Result<Node, String>;
+    fn parse_inner_text(&mut self) -> Result<Node, String>;
+    fn parse_variable_define(&mut self) -> Result<Node, String>;
+    fn parse_variable_use(&mut self) -> Result<Node, String>;
+    fn parse_bold(&mut self) -> Result<Node, String>;
+    fn parse_italics(&mut self) -> Result<Node, String>;
+    fn parse_list(&mut self) -> Result<Node, String>;
+    fn parse_list_items(&mut self) -> Result<Vec<Node>, String>;
+    fn parse_inner_list(&mut self) -> Result<Vec<Node>, String>;
+    fn parse_audio(&mut self) -> Result<Node, String>;
+    fn parse_video(&mut self) -> Result<Node, String>;
+    fn parse_newline(&mut self) -> Result<Node, String>;
 }
-///main parser, converts tokens into structured html, tracks variables, manages scope
+///main parser, converts tokens into a document tree, tracks variables, manages scope
 pub struct Parser { 
     tokens: Vec<Token>,
     pos:usize,
-    variables: HashMap<String, String>, //hashmap stores and manages variables
-    output: String,
+    scopes: Scopes<Vec<Node>>, //variables, one frame per open block
+    warnings: Vec<String>,
+    strict: bool, //turns warnings into errors
+    out_dir: Option<PathBuf>, //html and local media are written here when set
+    hash_assets: bool,
+    bundle_assets: bool, //local media is packed into one file, e.g. an epub
+    assets: Assets,
+    comments: Vec<String>, //comments skipped by peek, waiting to be placed in the tree
+    allowed_lints: Vec<LintId>,
 }
 
 impl Parser {
@@ -39,15 +57,49 @@ impl Parser {
         Parser { 
             tokens, 
             pos: 0 ,
-        variables: HashMap::new(),
-        output: String::new(),
+        scopes: Scopes::new(),
+        warnings: Vec::new(),
+        strict: false,
+        out_dir: None,
+        hash_assets: false,
+        bundle_assets: false,
+        assets: Assets::default(),
+        comments: Vec::new(),
+        allowed_lints: Vec::new(),
     }
 }
 
 
 
-///gets position
-    fn peek(&self) -> Option<&Token> {
+///with strict set, any warning fails the compile instead of being printed
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+///writes the html into out_dir and copies local media next to it,
+///hash_assets adds a content hash to copied file names for cache busting
+    pub fn set_out_dir(&mut self, out_dir: Option<PathBuf>, hash_assets: bool) {
+        self.out_dir = out_dir;
+        self.hash_assets = hash_assets;
+    }
+
+///local media is packed into a bundle with the page instead of an output folder, so it is
+///given paths inside the bundle the same way --out-dir does
+    pub fn set_bundle_assets(&mut self, bundle_assets: bool) {
+        self.bundle_assets = bundle_assets;
+    }
+
+///lint warnings with these ids are not reported
+    pub fn set_allowed_lints(&mut self, allowed_lints: Vec<LintId>) {
+        self.allowed_lints = allowed_lints;
+    }
+
+///gets position, comments are skipped here so they can sit between any two tokens
+    fn peek(&mut self) -> Option<&Token> {
+        while let Some(Token::Comment(text)) = self.tokens.get(self.pos) {
+            self.comments.push(text.clone());
+            self.pos += 1;
+        }
         self.tokens.get(self.pos)
     }
 ///moves position forward
@@ -66,24 +118,71 @@ impl Parser {
             Err(format!("Syntax Error: Unexpected end of input. Expected {:?}", expected))
         }
     }
+///adds a node to a block, placing any comments skipped before it first
+    fn push_node(&mut self, nodes: &mut Vec<Node>, node: Node) {
+        self.flush_comments(nodes);
+        nodes.push(node);
+    }
+///places skipped comments at the end of a block before it is closed
+    fn flush_comments(&mut self, nodes: &mut Vec<Node>) {
+        nodes.extend(self.comments.drain(..).map(Node::Comment));
+    }
+
+///parses the tokens of input_filename, local media is looked up next to it
+    pub fn parse_file(&mut self, input_filename: &str) -> Result<Document, String> {
+        let source_dir = Path::new(input_filename).parent().unwrap_or(Path::new(""));
+        let copied = self.out_dir.is_some() || self.bundle_assets;
+        self.assets = Assets::new(source_dir, copied, self.hash_assets);
+        self.parse()
+    }
+
+///local media found while parsing, with the addresses the output uses for them
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
 
-    pub fn compile_and_run(&mut self, input_filename: &str) -> Result <(), String> {
-        self.parse()?;
+///lints the document and prints the warnings, with --strict any warning is an error
+    pub fn check(&mut self, document: &Document) -> Result<(), String> {
+        self.warnings.extend(lint::check(document, &self.allowed_lints));
+        if self.strict && !self.warnings.is_empty() {
+            return Err(format!("{} warning(s) treated as errors (--strict):\n{}", self.warnings.len(), self.warnings.join("\n")));
+        }
+        for warning in self.warnings.drain(..) {
+            eprintln!("{}", warning);
+        }
+        Ok(())
+    }
 
-        let output_filename = input_filename.strip_suffix(".lolmd")
-        .unwrap_or(input_filename)
-        .strip_suffix(".lol")
-        .unwrap_or(input_filename)
-        .to_owned() + ".html";
+///writes the rendered output next to input_filename (or into out_dir with the local media),
+///format gives the file extension
+    pub fn write_output(&mut self, output: &str, format: Format, input_filename: &str) -> Result <(), String> {
+        let stem = [".lolmd", ".lol", ".json"].iter()
+        .find_map(|extension| input_filename.strip_suffix(extension))
+        .unwrap_or(input_filename);
+        let mut output_filename = stem.to_owned() + "." + format.extension();
+
+    if let Some(out_dir) = &self.out_dir {
+        if let Err(e) = fs::create_dir_all(out_dir) {
+            return Err(format!("Code Generation Error: Could not create output folder '{}': {}", out_dir.display(), e));
+        }
+        let file_name = Path::new(&output_filename).file_name().unwrap_or_default().to_owned();
+        output_filename = out_dir.join(file_name).to_string_lossy().into_owned();
+        self.assets.copy_to(out_dir)?;
+    }
 
     let mut file = match File::create(&output_filename){
         Ok(f) => f,
         Err(e) => return Err(format!("Code Generation Error: Could not create output file '{}': {}", output_filename, e)),   
     };
-    if let Err(e) = file.write_all(self.output.as_bytes()){
+    if let Err(e) = file.write_all(output.as_bytes()){
         return Err(format!("Code Generation Error: Could not write to output file: {}", e));
 
     }
+    //only a web page is opened in the browser
+    if format != Format::Html {
+        println!("Successfully compiled to '{}'.", output_filename);
+        return Ok(());
+    }
 let browser_result = Command::new("open") // mac only
             .arg(&output_filename)
             .spawn();
@@ -95,41 +194,30 @@ let browser_result = Command::new("open") // mac only
         Ok(())
     }
 
-    pub fn parse(&mut self) -> Result<(), String> {
-        self.output.push_str("<!DOCTYPE html>\n<html>\n");
+    pub fn parse(&mut self) -> Result<Document, String> {
         self.expect(&Token::Hai)?;
-        self.parse_comments()?;
-        self.parse_head()?;
-        self.output.push_str("<body>\n");
-        self.parse_body()?;
-        self.output.push_str("</body>\n");
+        //globals may come before the head so the title can use them
+        let mut prologue = self.parse_block_definitions()?;
+        self.flush_comments(&mut prologue);
+        let head = self.parse_head()?;
+        let body = self.parse_body()?;
         self.expect(&Token::Kthxbye)?;
-        self.output.push_str("</html>\n");
 
-        if self.peek().is_some() {
-            return Err(format!("Syntax Error: Content found after #KTHXBYE: {:?}", self.peek()));
+        //blank lines are fine, a comment is left for the trailing-content lint to report
+        self.parse_comments()?;
+        if let Some(token) = self.peek() {
+            return Err(format!("Syntax Error: Content found after #KTHXBYE: {:?}", token));
         }
+        let trailing = self.comments.drain(..).collect();
 
-        Ok(())
+        let mut document = Document { prologue, head, body, trailing };
+        whitespace::normalize(&mut document);
+        Ok(document)
     }
+    //skips blank text between elements, comments are already skipped by peek
     fn parse_comments(&mut self) -> Result<(), String> {
         loop {
          match self.peek(){ 
-             Some(&Token::Obtw) => {
-            self.advance();
-            let mut found_tldr = false;
-            while let Some(token) = self.peek() {
-                if token == &Token:: Tldr {
-                    self.advance();
-                    found_tldr = true;
-                    break;
-                }
-                self.advance();
-            }
-            if !found_tldr {
-                return Err("Syntax Error: Comment started with #OBTW but never closed with #TLDR.".to_string());
-            }
-    }, 
     Some(Token::Text(s)) => {
         if s.trim().is_empty() {
             self.advance();
@@ -147,7 +235,7 @@ let browser_result = Command::new("open") // mac only
 
     Ok(())
 }
-fn parse_head(&mut self) -> Result<(), String> {
+fn parse_head(&mut self) -> Result<Option<Vec<Node>>, String> {
     if self.peek() == Some(&Token::Maek){
         self.advance();
 
@@ -166,43 +254,50 @@ fn parse_head(&mut self) -> Result<(), String> {
             return Err(format!("Syntax Error: Expected HEAD after #MAEK, found {:?}", self.peek()));
         }
         
-        self.output.push_str("<head>\n");
-        self.parse_title()?;
+        let mut head = Vec::new();
+        let title = self.parse_title()?;
+        self.push_node(&mut head, title);
         self.parse_comments()?;
         self.expect(&Token::Oic)?;
-        self.output.push_str("</head>\n");
+        self.flush_comments(&mut head);
+        return Ok(Some(head));
     }
-    Ok(())
+    Ok(None)
 }
 
 
-fn parse_title(&mut self) -> Result<(), String> {
+fn parse_title(&mut self) -> Result<Node, String> {
     self.expect(&Token::Gimmeh)?;
     self.expect(&Token::Title)?;
 
-    self.output.push_str("<title>");
-
-    let title_text = match self.peek(){
-        Some(Token::Text(title)) => {
-            let title_clone = title.clone();
-            self.advance();
-            title_clone
-        },
-        _ => return Err ("Syntax Error: #GIMMEH TITLE must be followed by text.".to_string()),
-    };
-    self.output.push_str(&title_text);
+    //title text may mix plain text and #LEMME SEE references
+    let mut title = Vec::new();
+    loop {
+        match self.peek() {
+            Some(Token::Text(_)) => {
+                let text = self.parse_inner_text()?;
+                self.push_node(&mut title, text);
+            }
+            Some(Token::LemmeSee) => {
+                let variable = self.parse_variable_use()?;
+                self.push_node(&mut title, variable);
+            }
+            _ => break,
+        }
+    }
+    if title.is_empty() {
+        return Err ("Syntax Error: #GIMMEH TITLE must be followed by text.".to_string());
+    }
     self.expect(&Token::Mkay)?;
-    self.output.push_str("</title>\n");
-    Ok(())
+    self.flush_comments(&mut title);
+    Ok(Node::Title(title))
 }
 
 
-fn parse_body(&mut self) -> Result<(), String> { //parses doc body and goes until #KTHXBYE
+fn parse_body(&mut self) -> Result<Vec<Node>, String> { //parses doc body and goes until #KTHXBYE
+    let mut body = Vec::new();
     while self.peek() != Some(&Token::Kthxbye){
-
-        self.parse_comments()?;
-
-        match self.peek() { 
+        let node = match self.peek() { 
             Some(Token::Maek) => { //handles structured tokens
                 self.advance();
 
@@ -211,10 +306,7 @@ fn parse_body(&mut self) -> Result<(), String> { //parses doc body and goes unti
                         self.advance();
                         self.parse_paragraph()?
                     }
-                    Some(Token::List) => {
-                        self.advance();
-                        self.parse_list()?
-                    }
+                    Some(Token::List) => self.parse_list()?,
                 //if another value given, error given
                  _ => return Err(format!("Syntax Error: Expected PARAGRAF or LIST after #MAEK, found {:?}", self.peek())),
             }
@@ -226,47 +318,54 @@ fn parse_body(&mut self) -> Result<(), String> { //parses doc body and goes unti
 
         //body elements
            
-            Some(Token::Bold) | Some(Token::Italics) | Some(Token::Soundz(_)) | Some(Token::Vidz(_)) | Some(Token::Newline) => self.advance(),
+            Some(Token::Bold) | Some(Token::Italics) | Some(Token::Soundz(_)) | Some(Token::Vidz(_)) | Some(Token::Newline) => {
+                self.advance();
+                continue;
+            }
+            Some(Token::Kthxbye) => break,
             _ => {
                 return Err(format!("Syntax Error: Unexpected token in document body: {:?}", self.peek()));
             }
-        }
+        };
+        self.push_node(&mut body, node);
     }
-    Ok(())
+    self.flush_comments(&mut body);
+    Ok(body)
 }
 
 ///parses core paragraph content
 /// implements local variable scope
-fn parse_paragraph(&mut self) -> Result<(), String> {
-   //saves global state and hashmap is cloned to allow for local changes
-   let original_variables = self.variables.clone();
+fn parse_paragraph(&mut self) -> Result<Node, String> {
+    self.scopes.push();
 
-    self.output.push_str("<p>");
-//deals with local var definitions
-    if self.peek() == Some(&Token::IHaz) {
-        self.advance();
-        self.parse_variable_define_core()?;
-    }
-    
-    self.parse_comments()?;
+    let mut content = self.parse_block_definitions()?;
     //parses until #OIC
     while self.peek() != Some(&Token::Oic){
-        self.parse_inner_paragraph()?;
+        let node = self.parse_inner_paragraph()?;
+        self.push_node(&mut content, node);
     }
     self.expect(&Token::Oic)?;
-    self.output.push_str("</p>\n");
-//variables return to global state 
-self.variables = original_variables;
+    self.flush_comments(&mut content);
+//local variables end with the paragraph
+    self.scopes.pop();
 
-    Ok(())
+    Ok(Node::Paragraph(content))
+}
+///any number of #I HAZ definitions at the start of a block, they go into the block's frame
+fn parse_block_definitions(&mut self) -> Result<Vec<Node>, String> {
+    let mut definitions = Vec::new();
+    self.parse_comments()?;
+    while self.peek() == Some(&Token::IHaz) {
+        let definition = self.parse_variable_define()?;
+        self.push_node(&mut definitions, definition);
+    }
+    Ok(definitions)
 }
 
 
 
-fn parse_inner_paragraph(&mut self) -> Result<(), String> {
-    self.parse_comments()?;
-
-    match self.peek() {
+fn parse_inner_paragraph(&mut self) -> Result<Node, String> {
+    let node = match self.peek() {
         Some(Token::Gimmeh) => {
             self.advance();
             match self.peek() {
@@ -285,69 +384,95 @@ fn parse_inner_paragraph(&mut self) -> Result<(), String> {
                 _ => return Err(format!("Syntax Error: Expected LIST after #MAEK inside PARAGRAF, found {:?}",self.peek()))
             }
         },
-        Some(Token::I) => {
-            return Err("Syntax Error: Variable definition #I HAZ... not allowed mid-paragraph.".to_string());
+        Some(Token::IHaz) => {
+            return Err("Syntax Error: Variable definition #I HAZ... is only allowed at the start of a block.".to_string());
         }
         Some(Token::LemmeSee) => self.parse_variable_use()?,
         Some(Token::Text(_)) => self.parse_inner_text()?,
         _ => return Err (format!("Syntax Error: Unexpected token inside paragraph: {:?}", self.peek())),
-    }
-    Ok(())
+    };
+    Ok(node)
 }
-fn parse_variable_define(&mut self) -> Result<(), String> {
+fn parse_variable_define(&mut self) -> Result<Node, String> {
     self.expect(&Token::IHaz)?;
     self.parse_variable_define_core()
 }
 //main portion for assigning values to variables
-fn parse_variable_define_core(&mut self) -> Result<(), String> {
+fn parse_variable_define_core(&mut self) -> Result<Node, String> {
     let var_name  = match self.peek() {
-        Some(Token::Text(name)) => {
+        Some(Token::VarDef(name)) => {
         let name_string = name.clone();
         self.advance();
         name_string
     }
-        _ => return Err("Syntax Error: Expected variable name (Text) after #I HAZ.".to_string())
+        _ => return Err("Syntax Error: Expected variable name after #I HAZ.".to_string())
 };
     self.expect(&Token::ItIz)?;
     
-    let var_value = match self.peek(){
-        Some(Token::Text(value)) => {
-            let value_string = value.clone();
-            self.advance();
-            value_string
-        }
-        _ => return Err("Syntax Error: Expected variable value (Text) after #IT IZ.".to_string()),
-    };
+    //the value is an inline fragment, kept as a tree and rendered again wherever it is used
+    let mut var_value = Vec::new();
+    loop {
+        let node = match self.peek() {
+            Some(Token::VarVal(text)) => {
+                let node = Node::Text(text.clone());
+                self.advance();
+                node
+            }
+            Some(Token::Text(_)) => self.parse_inner_text()?,
+            Some(Token::LemmeSee) => self.parse_variable_use()?,
+            Some(Token::Gimmeh) => {
+                self.advance();
+                match self.peek() {
+                    Some(Token::Bold) => self.parse_bold()?,
+                    Some(Token::Italics) => self.parse_italics()?,
+                    Some(Token::Newline) => self.parse_newline()?,
+                    _ => return Err(format!("Syntax Error: Only BOLD/ITALICS/NEWLINE allowed in a variable value: {:?}", self.peek())),
+                }
+            }
+            _ => break,
+        };
+        self.push_node(&mut var_value, node);
+    }
+    if var_value.is_empty() {
+        return Err("Syntax Error: Expected variable value after #IT IZ.".to_string());
+    }
     self.expect(&Token::Mkay)?;
+    self.flush_comments(&mut var_value);
 
     self.parse_comments()?;
 
     
-    self.variables.insert(var_name, var_value);
-    Ok(())
+    self.scopes.define(var_name.clone(), var_value.clone());
+    Ok(Node::Define { name: var_name, value: var_value })
 }
-fn parse_variable_use(&mut self) -> Result<(), String> {
+fn parse_variable_use(&mut self) -> Result<Node, String> {
     self.expect(&Token::LemmeSee)?;
 
     let var_name = match self.peek(){
-        Some(Token::Text(name)) => {
+        Some(Token::VarUse(name)) => {
             let name_string = name.clone();
             self.advance();
             name_string
         }
-        _ => return Err ("Syntax Error: Expected variable name (Text) after #LEMME SEE.".to_string()),
+        _ => return Err ("Syntax Error: Expected variable name after #LEMME SEE.".to_string()),
     }; 
     self.expect(&Token::Mkay)?;
 
-    if let Some(value) = self.variables.get(&var_name) {
-        self.output.push_str(value);
-    } else {
-        return Err(format!("Semantic Error: Variable '{}' used but not defined.", var_name));
+    match self.scopes.lookup(&var_name) {
+        Some(value) => Ok(Node::Variable { value: value.clone(), name: var_name }),
+        None => Err(format!("Semantic Error: Variable '{}' used but not defined.{}", var_name, suggest::hint(&var_name, self.scopes.names()))),
     }
-    Ok(())
+}
+///reads #LEMME SEE name #MKAY where only text can go, markup in the value is dropped.
+///the use is kept in variables so the lint and the ast export still see it
+fn parse_variable_text(&mut self, variables: &mut Vec<Node>) -> Result<String, String> {
+    let variable = self.parse_variable_use()?;
+    let text = ast::plain_text(std::slice::from_ref(&variable));
+    variables.push(variable);
+    Ok(text)
 }
 
-fn parse_gimmeh_body_element(&mut self) -> Result<(), String> {
+fn parse_gimmeh_body_element(&mut self) -> Result<Node, String> {
     self.expect(&Token::Gimmeh)?;
     match self.peek() {
         Some(Token::Bold) => self.parse_bold(),
@@ -360,7 +485,7 @@ fn parse_gimmeh_body_element(&mut self) -> Result<(), String> {
  }
 
 //parses audio content
-fn parse_audio(&mut self) -> Result<(), String> {
+fn parse_audio(&mut self) -> Result<Node, String> {
    let audio_src =  match self.peek(){
         Some(Token::Soundz(src)) =>{
             let src_clone = src.clone();
@@ -369,17 +494,17 @@ fn parse_audio(&mut self) -> Result<(), String> {
         },
         _ => return Err ("Internal Error: parse audio called without Soundz token.".to_string()),
     };
+    let media = self.parse_media(&audio_src)?;
     self.expect(&Token::Mkay)?;
-    self.output.push_str(&format!("<audio controls><source src=\"{}\" type=\"audio/mp3\"></audio>\n", audio_src));
-    Ok(())
+    media::check_audio(&media.options)?;
+    Ok(Node::Audio(media))
 }
-fn parse_newline(&mut self) -> Result<(), String> {
+fn parse_newline(&mut self) -> Result<Node, String> {
     self.expect(&Token::Newline)?;
-    self.output.push_str("<br>\n");
-    Ok(())
+    Ok(Node::Newline)
 }
 //video parsing
-fn parse_video(&mut self) -> Result<(), String> {
+fn parse_video(&mut self) -> Result<Node, String> {
        let video_src =  match self.peek() {
             Some(Token::Vidz(src)) => {
                 let src_clone = src.clone();
@@ -388,11 +513,74 @@ fn parse_video(&mut self) -> Result<(), String> {
             },
             _ => return Err("Internal Error: parse_video called without Vidz token.".to_string()),
         };
+        let media = self.parse_media(&video_src)?;
         self.expect(&Token::Mkay)?;
-        self.output.push_str(&format!("<iframe src=\"{}\" frameborder=\"0\" allowfullscreen></iframe>\n", video_src));
-        Ok(())
+        media::check_video(&media.address, &media.options, &mut self.warnings)?;
+        Ok(Node::Video(media))
+    }
+    //validates the address and reads the optional settings before #MKAY, e.g. autoplay width=640
+    fn parse_media(&mut self, prefix: &str) -> Result<Media, String> {
+        let mut src = prefix.to_string();
+        let mut options_text = String::new();
+        let mut variables = Vec::new();
+
+        //#LEMME SEE right after the address is part of it, and so is the word that follows,
+        //e.g. #GIMMEH VIDZ #LEMME SEE base #MKAY/intro.mp4 #MKAY
+        while self.peek() == Some(&Token::LemmeSee) {
+            let value = self.parse_variable_text(&mut variables)?;
+            src.push_str(&html::encode_url(value.trim()));
+
+            if let Some(Token::Text(text)) = self.peek() {
+                let text = text.trim_start().to_string();
+                let word = text.split_whitespace().next().unwrap_or("");
+                if media::is_option(word) {
+                    break;
+                }
+                self.advance();
+                src.push_str(word);
+                options_text = text[word.len()..].to_string();
+                if !options_text.trim().is_empty() {
+                    break;
+                }
+            }
+        }
+        let address = self.parse_local_address(&src)?;
+
+        //options may take their values from variables, e.g. width=#LEMME SEE w #MKAY
+        loop {
+            match self.peek() {
+                Some(Token::Text(text)) => {
+                    options_text.push_str(text);
+                    self.advance();
+                }
+                Some(Token::LemmeSee) => {
+                    let value = self.parse_variable_text(&mut variables)?;
+                    options_text.push_str(&value);
+                    options_text.push(' ');
+                }
+                _ => break,
+            }
+        }
+        //a word that is not an option means the address itself had a space in it
+        if let Some(word) = options_text.split_whitespace().next().filter(|w| !media::is_option(w)) {
+            return Err(format!("Address Error: Address '{} {}' contains a space; percent-encode it (a space is %20).", src, word));
+        }
+        let mut options = MediaOptions::parse(&options_text)?;
+        if let Some(captions) = &options.captions {
+            options.captions = Some(self.parse_local_address(captions)?.text);
+        }
+        Ok(Media { address, options, variables })
+    }
+    //relative addresses are checked on disk and may be rewritten to where the copy will live
+    fn parse_local_address(&mut self, src: &str) -> Result<Address, String> {
+        let address = Address::parse(src, &mut self.warnings)?;
+        let resolved = self.assets.resolve(&address, &mut self.warnings);
+        if resolved == address.text {
+            return Ok(address);
+        }
+        Address::parse(&resolved, &mut Vec::new())
     }
-    fn parse_inner_text(&mut self) -> Result<(), String> {
+    fn parse_inner_text(&mut self) -> Result<Node, String> {
        let text_content =  match self.peek() {
             Some(Token::Text(text)) => {
                 let text_clone = text.clone();
@@ -401,51 +589,61 @@ fn parse_video(&mut self) -> Result<(), String> {
             },
             _ => return Err("Internal Error: parse_inner_text called without Text token.".to_string())
         };
-        self.output.push_str(&text_content);
-        Ok(())
+        Ok(Node::Text(text_content))
     }
 // functions for text design
-fn parse_bold(&mut self) -> Result<(), String> {
+fn parse_bold(&mut self) -> Result<Node, String> {
     self.expect(&Token::Bold)?;
-    self.output.push_str("<b>");
-    match self.peek() {
-        Some(Token::Text(_)) => self.parse_inner_text()?,
-        _ => {}
-    }
-    self.expect(&Token::Mkay)?;
-    self.output.push_str("</b>");
-    Ok(())
+    let content = self.parse_styled_text()?;
+    Ok(Node::Bold(content))
 }
-fn parse_italics(&mut self) -> Result<(), String> {
+fn parse_italics(&mut self) -> Result<Node, String> {
     self.expect(&Token::Italics)?;
-    self.output.push_str("<i>");
-    match self.peek() {
-        Some(Token::Text(_)) => self.parse_inner_text()?,
-        _ => {}
+    let content = self.parse_styled_text()?;
+    Ok(Node::Italics(content))
+}
+//text and variables up to the closing #MKAY of a BOLD or ITALICS
+fn parse_styled_text(&mut self) -> Result<Vec<Node>, String> {
+    let mut content = Vec::new();
+    loop {
+        let node = match self.peek() {
+            Some(Token::Text(_)) => self.parse_inner_text()?,
+            Some(Token::LemmeSee) => self.parse_variable_use()?,
+            _ => break,
+        };
+        self.push_node(&mut content, node);
     }
     self.expect(&Token::Mkay)?;
-    self.output.push_str("</i>");
-    Ok(())
-}//parses unordered list 
-fn parse_list(&mut self) -> Result<(), String> {
+    self.flush_comments(&mut content);
+    Ok(content)
+}
+//parses unordered list 
+fn parse_list(&mut self) -> Result<Node, String> {
     self.expect(&Token::List)?;
-    self.output.push_str("<ul>\n");
-    self.parse_list_items()?;
+    self.scopes.push();
+    let mut content = self.parse_block_definitions()?;
+    let items = self.parse_list_items()?;
+    content.extend(items);
     self.expect(&Token::Oic)?;
-    self.output.push_str("</ul>\n");
-    Ok(())
+    self.flush_comments(&mut content);
+    self.scopes.pop();
+    Ok(Node::List(content))
 }
 //handles list items inside a list
-fn parse_list_items(&mut self) -> Result<(), String> {
+fn parse_list_items(&mut self) -> Result<Vec<Node>, String> {
+    let mut items = Vec::new();
     let mut item_count = 0;
     self.parse_comments()?;
     while self.peek() == Some(&Token::Gimmeh) {
+        //comments before the item belong to the list, not inside the item
+        self.flush_comments(&mut items);
         self.advance();
         self.expect(&Token::Item)?;
-        self.output.push_str("<li>");
+        self.scopes.push();
+        let mut content = self.parse_block_definitions()?;
 
         while self.peek() != Some(&Token::Mkay) && self.peek().is_some() {
-            match self.peek() {
+            let node = match self.peek() {
                 Some(Token::Gimmeh) => {
                     self.advance();
                     match self.peek() {
@@ -454,47 +652,51 @@ fn parse_list_items(&mut self) -> Result<(), String> {
                         _ => return Err (format!("Syntax Error: Only BOLD/ITALICS allowed in list item: {:?}", self.peek()))
                     }
                 },
+                Some(Token::LemmeSee) => self.parse_variable_use()?,
                 Some(Token::Text(_)) => self.parse_inner_text()?,
                 _ => break,
-            }
+            };
+            self.push_node(&mut content, node);
         }
         self.expect(&Token::Mkay)?;
-        self.output.push_str("</li>\n");
+        self.flush_comments(&mut content);
+        self.scopes.pop();
+        self.push_node(&mut items, Node::Item(content));
         item_count += 1;
         self.parse_comments()?;
     }
     if item_count == 0 {
         return Err("Syntax Error: #MAEK LIST must contain at least one #GIMMEH ITEM.".to_string());
     }
-    Ok(())
+    Ok(items)
 }
-fn parse_inner_list(&mut self) -> Result<(), String> {
+#[allow(dead_code)]
+fn parse_inner_list(&mut self) -> Result<Vec<Node>, String> {
     self.parse_list_items()
 }
 
 
 }
 impl SyntaxAnalyzer for Parser {
-    fn parse_lolcode(&mut self) -> Result<(), String> {
+    fn parse_lolcode(&mut self) -> Result<Document, String> {
         self.parse()
     }
 
-    fn parse_head(&mut self) -> Result<(), String> { self.parse_head() }
-    fn parse_title(&mut self) -> Result<(), String> { self.parse_title() }
+    fn parse_head(&mut self) -> Result<Option<Vec<Node>>, String> { self.parse_head() }
+    fn parse_title(&mut self) -> Result<Node, String> { self.parse_title() }
     fn parse_comment(&mut self) -> Result<(), String> { self.parse_comments() }
-    fn parse_body(&mut self) -> Result<(), String> { self.parse_body() }
-    fn parse_paragraph(&mut self) -> Result<(), String> { self.parse_paragraph() }
-    fn parse_inner_paragraph(&mut self) -> Result<(), String> { self.parse_inner_paragraph() }
-    fn parse_inner_text(&mut self) -> Result<(), String> { self.parse_inner_text() }
-    fn parse_variable_define(&mut self) -> Result<(), String> { self.parse_variable_define() }
-    fn parse_variable_use(&mut self) -> Result<(), String> { self.parse_variable_use() }
-    fn parse_bold(&mut self) -> Result<(), String> { self.parse_bold() }
-    fn parse_italics(&mut self) -> Result<(), String> { self.parse_italics() }
-    fn parse_list(&mut self) -> Result<(), String> { self.parse_list() }
-    fn parse_list_items(&mut self) -> Result<(), String> { self.parse_list_items() }
-    fn parse_inner_list(&mut self) -> Result<(), String> { self.parse_inner_list() }
-    fn parse_audio(&mut self) -> Result<(), String> { self.parse_audio() }
-    fn parse_video(&mut self) -> Result<(), String> { self.parse_video() }
-    fn parse_newline(&mut self) -> Result<(), String> { self.parse_newline() }
+    fn parse_body(&mut self) -> Result<Vec<Node>, String> { self.parse_body() }
+    fn parse_paragraph(&mut self) -> Result<Node, String> { self.parse_paragraph() }
+    fn parse_inner_paragraph(&mut self) -> Result<Node, String> { self.parse_inner_paragraph() }
+    fn parse_inner_text(&mut self) -> Result<Node, String> { self.parse_inner_text() }
+    fn parse_variable_define(&mut self) -> Result<Node, String> { self.parse_variable_define() }
+    fn parse_variable_use(&mut self) -> Result<Node, String> { self.parse_variable_use() }
+    fn parse_bold(&mut self) -> Result<Node, String> { self.parse_bold() }
+    fn parse_italics(&mut self) -> Result<Node, String> { self.parse_italics() }
+    fn parse_list(&mut self) -> Result<Node, String> { self.parse_list() }
+    fn parse_list_items(&mut self) -> Result<Vec<Node>, String> { self.parse_list_items() }
+    fn parse_inner_list(&mut self) -> Result<Vec<Node>, String> { self.parse_inner_list() }
+    fn parse_audio(&mut self) -> Result<Node, String> { self.parse_audio() }
+    fn parse_video(&mut self) -> Result<Node, String> { self.parse_video() }
+    fn parse_newline(&mut self) -> Result<Node, String> { self.parse_newline() }
 }
-
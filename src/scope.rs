@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+/// symbol table as a chain of frames, the document's globals first and the innermost block last
+pub struct Scopes<T> {
+    frames: Vec<HashMap<String, T>>,
+}
+
+impl<T> Scopes<T> {
+    pub fn new() -> Self {
+        Scopes { frames: vec![HashMap::new()] }
+    }
+
+    /// entering a block (paragraph, list, list item) opens a frame for its definitions
+    pub fn push(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    /// leaving a block drops its definitions and returns them, the global frame is emptied but never popped
+    pub fn pop(&mut self) -> Vec<(String, T)> {
+        let frame = if self.frames.len() > 1 {
+            self.frames.pop().unwrap_or_default()
+        } else {
+            std::mem::take(&mut self.frames[0])
+        };
+        frame.into_iter().collect()
+    }
+
+    /// true while no block is open
+    pub fn is_global(&self) -> bool {
+        self.frames.len() == 1
+    }
+
+    /// defines a variable in the innermost frame
+    pub fn define(&mut self, name: String, value: T) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.insert(name, value);
+        }
+    }
+
+    /// finds a variable, walking outward from the innermost frame
+    pub fn lookup(&self, name: &str) -> Option<&T> {
+        self.frames.iter().rev().find_map(|frame| frame.get(name))
+    }
+
+    /// every name visible from the innermost frame, sorted so hints built from them don't
+    /// depend on hash order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.frames.iter().flat_map(|frame| frame.keys().map(|name| name.as_str())).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    pub fn lookup_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.frames.iter_mut().rev().find_map(|frame| frame.get_mut(name))
+    }
+
+    /// only the innermost frame
+    pub fn lookup_local(&self, name: &str) -> Option<&T> {
+        self.frames.last().and_then(|frame| frame.get(name))
+    }
+
+    /// only the document's globals
+    pub fn lookup_global(&self, name: &str) -> Option<&T> {
+        self.frames[0].get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inner_frame_shadows_outer() {
+        let mut scopes = Scopes::new();
+        scopes.define("answer".to_string(), "happiness".to_string());
+
+        scopes.push();
+        scopes.define("answer".to_string(), "42".to_string());
+        scopes.push();
+        assert_eq!(scopes.lookup("answer"), Some(&"42".to_string()));
+        scopes.pop();
+        scopes.pop();
+
+        assert_eq!(scopes.lookup("answer"), Some(&"happiness".to_string()));
+    }
+
+    #[test]
+    fn test_block_definitions_end_with_block() {
+        let mut scopes = Scopes::new();
+        scopes.push();
+        scopes.define("local".to_string(), "x".to_string());
+        scopes.pop();
+
+        assert_eq!(scopes.lookup("local"), None);
+    }
+
+    #[test]
+    fn test_names_are_sorted() {
+        let mut scopes = Scopes::new();
+        for name in ["cat", "bat", "hat"] {
+            scopes.define(name.to_string(), String::new());
+        }
+        scopes.push();
+        scopes.define("bat".to_string(), String::new());
+        assert_eq!(scopes.names(), ["bat", "cat", "hat"]);
+        assert_eq!(crate::suggest::closest("rat", scopes.names()), Some("bat"));
+    }
+}
@@ -0,0 +1,71 @@
+//"did you mean" hints for misspelled keywords and variable names
+
+/// the candidate closest to word, if it is close enough to be a likely typo
+pub fn closest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.trim().to_lowercase();
+    if word.is_empty() {
+        return None;
+    }
+    //one edit for short words, about one per three letters for longer ones
+    let limit = (word.chars().count() / 3).max(1);
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        let distance = edit_distance(&word, &candidate.trim().to_lowercase());
+        if distance <= limit && best.is_none_or(|(d, _)| distance < d) {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+/// " (did you mean `x`?)" or nothing, ready to append to an error message
+pub fn hint<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match closest(word, candidates) {
+        Some(candidate) => format!(" (did you mean `{}`?)", candidate.trim()),
+        None => String::new(),
+    }
+}
+
+/// edit distance where inserting, deleting or changing a character, or swapping two
+/// neighbouring ones, costs 1
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    //d[i][j] is the distance between the first i chars of a and the first j of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "oic"), 3);
+        assert_eq!(edit_distance("bold", "bold"), 0);
+        assert_eq!(edit_distance("mkya", "mkay"), 1);
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(closest("BOLDD", ["BOLD", "ITALICS", "ITEM"]), Some("BOLD"));
+        assert_eq!(closest("answr", ["answer", "question"]), Some("answer"));
+        assert_eq!(closest("xyz", ["answer", "question"]), None);
+        assert_eq!(hint("mkya", ["MKAY", "MAEK"]), " (did you mean `MKAY`?)");
+    }
+}
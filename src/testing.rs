@@ -0,0 +1,91 @@
+//helpers for the backend tests: parsing a source, the test files and their golden output, and
+//the words a page shows, so each backend's own tests are left with what is special to it
+use crate::ast::Document;
+use crate::cst::Cst;
+use crate::parser::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// a document with a bit of everything: a title, a global with markup, italics, a line break, a
+/// comment, a list with bold in an item and both kinds of media
+pub const SAMPLE: &str = "#HAI #MAEK HEAD #GIMMEH TITLE Test 8 #MKAY #OIC
+    #I HAZ answer #IT IZ #GIMMEH BOLD happiness #MKAY #MKAY
+    #MAEK PARAGRAF The meaning of #GIMMEH ITALICS life #MKAY is #LEMME SEE answer #MKAY #GIMMEH NEWLINE or so #OBTW they say #TLDR #OIC
+    #MAEK LIST #GIMMEH ITEM fish and #GIMMEH BOLD chips #MKAY and mushy peas #MKAY #GIMMEH ITEM tea #MKAY #OIC
+    #GIMMEH SOUNDZ theme.mp3 #MKAY #GIMMEH VIDZ https://vimeo.com/76979871 #MKAY #KTHXBYE";
+
+/// the document for a source that has to compile
+pub fn parse(source: &str) -> Document {
+    let cst = Cst::parse(source);
+    assert!(cst.errors.is_empty(), "{:?}\n{}", cst.errors, source);
+    Parser::new(cst.tokens()).parse().unwrap_or_else(|e| panic!("{}\n{}", e, source))
+}
+
+/// a tests/*.lol file that compiles
+pub struct TestFile {
+    pub path: PathBuf,
+    pub source: String,
+    pub cst: Cst,
+    pub document: Document,
+}
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// every tests/*.lol file except the ones that are there to fail
+pub fn test_files() -> Vec<TestFile> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(tests_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|e| e != "lol") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        let cst = Cst::parse(&source);
+        if let Ok(document) = Parser::new(cst.tokens()).parse()
+            && cst.errors.is_empty()
+        {
+            files.push(TestFile { path, source, cst, document });
+        }
+    }
+    assert!(files.len() >= 6);
+    files
+}
+
+/// tests/TestN.<extension> is the expected output of render for tests/TestN.lol
+pub fn check_golden_files(extension: &str, render: impl Fn(&Document) -> String) {
+    let mut checked = 0;
+    for entry in fs::read_dir(tests_dir()).unwrap() {
+        let golden = entry.unwrap().path();
+        if golden.extension().is_none_or(|e| e != extension) {
+            continue;
+        }
+        let source = fs::read_to_string(golden.with_extension("lol")).unwrap();
+        assert_eq!(render(&parse(&source)), fs::read_to_string(&golden).unwrap(), "{}", golden.display());
+        checked += 1;
+    }
+    assert!(checked >= 6, "only {} .{} golden files", checked, extension);
+}
+
+/// the words of a page, with the tags in markup spelled the way another format spells them and
+/// every other tag and comment left out
+pub fn html_words(html: &str, markup: &[(&str, &str)]) -> Vec<String> {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some((tag, spelling)) = markup.iter().find(|(tag, _)| rest.starts_with(tag)) {
+            text.push_str(spelling);
+            rest = &rest[tag.len()..];
+            continue;
+        }
+        let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+        text.push(' ');
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+        .split_whitespace().map(str::to_string).collect()
+}
@@ -0,0 +1,210 @@
+//plain text generation for email bodies and terminal previews: markup is dropped, paragraphs
+//are wrapped, lists get bullets and media players become [audio: url] / [video: url]
+use crate::ast::{self, Document, Node};
+
+/// columns a line is wrapped at unless --width says otherwise
+pub const DEFAULT_WIDTH: usize = 72;
+
+const BULLET: &str = "* ";
+const LIST_INDENT: usize = 2;
+
+/// renders a parsed document as plain text wrapped at width columns, 0 turns wrapping off
+pub fn render(document: &Document, width: usize) -> String {
+    let mut renderer = TextRenderer { blocks: Vec::new(), current: String::new(), width };
+    renderer.render_nodes(&document.prologue);
+    if let Some(head) = &document.head {
+        renderer.render_nodes(head);
+    }
+    renderer.render_nodes(&document.body);
+    renderer.end_paragraph();
+
+    let mut output = renderer.blocks.join("\n\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+struct TextRenderer {
+    blocks: Vec<String>, // finished blocks, separated by a blank line in the output
+    current: String,     // the paragraph being written, \n where #GIMMEH NEWLINE was
+    width: usize,
+}
+
+impl TextRenderer {
+    fn render_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.render_node(node);
+        }
+    }
+
+    fn render_node(&mut self, node: &Node) {
+        match node {
+            //the title is underlined, like a heading in a plain text email
+            Node::Title(content) => {
+                let title = ast::plain_text(content).trim().to_string();
+                if !title.is_empty() {
+                    self.end_paragraph();
+                    let underline = "=".repeat(title.chars().count());
+                    self.blocks.push(format!("{}\n{}", title, underline));
+                }
+            }
+            Node::Paragraph(content) => {
+                self.end_paragraph();
+                self.render_nodes(content);
+                self.end_paragraph();
+            }
+            Node::List(_) | Node::Item(_) => {
+                self.end_paragraph();
+                let items = match node {
+                    Node::List(items) => items.as_slice(),
+                    _ => std::slice::from_ref(node),
+                };
+                let mut lines = Vec::new();
+                self.list(items, 0, &mut lines);
+                if !lines.is_empty() {
+                    self.blocks.push(lines.join("\n"));
+                }
+            }
+            Node::Audio(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!("[audio: {}]", m.address.text));
+            }
+            Node::Video(m) => {
+                self.end_paragraph();
+                self.blocks.push(format!("[video: {}]", m.address.text));
+            }
+            //resolved the same way as for html, the value is rendered again at every use
+            Node::Variable { value, .. } => self.render_nodes(value),
+            _ => inline(&mut self.current, std::slice::from_ref(node)),
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        let paragraph = std::mem::take(&mut self.current);
+        let lines = wrap(&paragraph, self.width, "", "");
+        if !lines.is_empty() {
+            self.blocks.push(lines.join("\n"));
+        }
+    }
+
+    //each item is a bullet, its wrapped lines line up under the text after the bullet and
+    //nested lists are indented one step further
+    fn list(&self, content: &[Node], depth: usize, lines: &mut Vec<String>) {
+        for node in content {
+            match node {
+                Node::List(items) => self.list(items, depth + 1, lines),
+                Node::Item(item) => {
+                    let mut text = String::new();
+                    inline(&mut text, item);
+                    let indent = " ".repeat(depth * LIST_INDENT);
+                    let first = format!("{}{}", indent, BULLET);
+                    let rest = " ".repeat(first.len());
+                    let wrapped = wrap(&text, self.width, &first, &rest);
+                    if wrapped.is_empty() {
+                        lines.push(first.trim_end().to_string());
+                    }
+                    lines.extend(wrapped);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+//the text of inline content, markup and comments are dropped and line breaks kept
+fn inline(output: &mut String, nodes: &[Node]) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Bold(content) | Node::Italics(content) => inline(output, content),
+            Node::Variable { value, .. } => inline(output, value),
+            Node::Newline => output.push('\n'),
+            _ => {}
+        }
+    }
+}
+
+/// breaks text into lines of at most width columns, at spaces. the first line starts with
+/// first, the others with rest. a word longer than a line gets a line of its own and a \n
+/// in the text always starts a new line
+pub fn wrap(text: &str, width: usize, first: &str, rest: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+    let mut words = 0;
+    for segment in text.trim().split('\n') {
+        for word in segment.split_whitespace() {
+            let word_width = word.chars().count();
+            if words > 0 && width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                words = 0;
+            }
+            if words == 0 {
+                line = if lines.is_empty() { first } else { rest }.to_string();
+                line_width = line.chars().count();
+            } else {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(word);
+            line_width += word_width;
+            words += 1;
+        }
+        if words > 0 {
+            lines.push(std::mem::take(&mut line));
+            words = 0;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+    use crate::testing;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("the meaning of life is happiness", 14, "", ""), vec!["the meaning of", "life is", "happiness"]);
+        assert_eq!(wrap("one two\nthree", 0, "* ", "  "), vec!["* one two", "  three"]);
+        assert_eq!(wrap("a supercalifragilistic word", 10, "", ""), vec!["a", "supercalifragilistic", "word"]);
+        assert!(wrap("  \n ", 10, "", "").is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let expected = "Test 8
+======
+
+The meaning of life
+is happiness
+or so
+
+* fish and chips and
+  mushy peas
+* tea
+
+[audio: theme.mp3]
+
+[video: https://vimeo.com/76979871]
+";
+        assert_eq!(render(&testing::parse(testing::SAMPLE), 20), expected);
+    }
+
+    #[test]
+    fn test_every_test_file_shows_the_same_words_as_html() {
+        for file in testing::test_files() {
+            //media is the only thing html shows as tags and text as words
+            let text: String = render(&file.document, 30).lines()
+                .filter(|line| !line.starts_with("[audio: ") && !line.starts_with("[video: "))
+                .filter(|line| !line.chars().all(|c| c == '='))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            let words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+            assert_eq!(words, testing::html_words(&html::render(&file.document, false), &[]), "{}", file.path.display());
+            assert!(text.lines().all(|line| line.chars().count() <= 30), "{}", file.path.display());
+        }
+    }
+}
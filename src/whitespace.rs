@@ -0,0 +1,147 @@
+//the whitespace model, applied to the document tree once it is parsed:
+// - a run of spaces, tabs and newlines in text is one space
+// - whitespace at the edges of a block or span (p, li, title, b, i, a variable's value) is dropped
+// - between two inline elements at most one space is kept, and only if the source had one
+// - a line break, list or media player starts a new line, so spaces next to it are dropped as well
+use crate::ast::{Document, Node};
+
+/// normalizes the whitespace of every text in the document
+pub fn normalize(document: &mut Document) {
+    normalize_block(&mut document.prologue);
+    if let Some(head) = &mut document.head {
+        normalize_block(head);
+    }
+    normalize_block(&mut document.body);
+}
+
+/// normalizes a sequence of nodes whose first and last text touch a block edge
+pub fn normalize_block(nodes: &mut Vec<Node>) {
+    for node in nodes.iter_mut() {
+        match node {
+            Node::Title(children) | Node::Paragraph(children) | Node::List(children) | Node::Item(children)
+            | Node::Bold(children) | Node::Italics(children) => normalize_block(children),
+            Node::Define { value, .. } | Node::Variable { value, .. } => normalize_block(value),
+            Node::Text(text) => *text = collapse(text),
+            Node::Newline | Node::Audio(_) | Node::Video(_) | Node::Comment(_) => {}
+        }
+    }
+
+    //forwards: drop a leading space at an edge or after another space
+    let mut space_allowed = false;
+    for node in nodes.iter_mut() {
+        match node {
+            Node::Text(text) => {
+                if !space_allowed && text.starts_with(' ') {
+                    text.remove(0);
+                }
+                if !text.is_empty() {
+                    space_allowed = !text.ends_with(' ');
+                }
+            }
+            _ if is_line_edge(node) => space_allowed = false,
+            _ if is_transparent(node) => {}
+            _ => space_allowed = true,
+        }
+    }
+
+    //backwards: drop a trailing space at an edge
+    let mut at_edge = true;
+    for node in nodes.iter_mut().rev() {
+        match node {
+            Node::Text(text) => {
+                if at_edge && text.ends_with(' ') {
+                    text.pop();
+                }
+                if !text.is_empty() {
+                    at_edge = false;
+                }
+            }
+            _ if is_line_edge(node) => at_edge = true,
+            _ if is_transparent(node) => {}
+            _ => at_edge = false,
+        }
+    }
+
+    nodes.retain(|node| !matches!(node, Node::Text(text) if text.is_empty()));
+}
+
+//nodes that start a new line in the output
+fn is_line_edge(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::Newline | Node::Paragraph(_) | Node::List(_) | Node::Item(_) | Node::Title(_) | Node::Audio(_) | Node::Video(_)
+    )
+}
+
+//nodes that do not show up between the words around them
+fn is_transparent(node: &Node) -> bool {
+    matches!(node, Node::Comment(_) | Node::Define { .. })
+}
+
+fn collapse(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Node {
+        Node::Text(s.to_string())
+    }
+
+    fn normalized(mut nodes: Vec<Node>) -> Vec<Node> {
+        normalize_block(&mut nodes);
+        nodes
+    }
+
+    #[test]
+    fn test_runs_collapse() {
+        assert_eq!(normalized(vec![text("a \n\t b")]), vec![text("a b")]);
+    }
+
+    #[test]
+    fn test_block_and_span_edges_are_trimmed() {
+        assert_eq!(normalized(vec![text("\n\t The meaning  ")]), vec![text("The meaning")]);
+        assert_eq!(
+            normalized(vec![text("of "), Node::Bold(vec![text("life ")]), text(" is")]),
+            vec![text("of "), Node::Bold(vec![text("life")]), text(" is")]
+        );
+        assert_eq!(normalized(vec![text("   ")]), vec![]);
+    }
+
+    #[test]
+    fn test_one_space_between_inline_elements() {
+        let answer = Node::Variable { name: "answer".to_string(), value: vec![text(" 42 ")] };
+        //only the space the source had, and never two
+        assert_eq!(
+            normalized(vec![text("is "), answer.clone(), text(". "), Node::Comment("x".to_string()), text(" Next")]),
+            vec![text("is "), Node::Variable { name: "answer".to_string(), value: vec![text("42")] }, text(". "), Node::Comment("x".to_string()), text("Next")]
+        );
+        assert_eq!(
+            normalized(vec![Node::Italics(vec![text("a")]), Node::Bold(vec![text("b")]), text("c")]),
+            vec![Node::Italics(vec![text("a")]), Node::Bold(vec![text("b")]), text("c")]
+        );
+    }
+
+    #[test]
+    fn test_line_breaks_are_edges() {
+        assert_eq!(
+            normalized(vec![text("one "), Node::Newline, text(" two")]),
+            vec![text("one"), Node::Newline, text("two")]
+        );
+    }
+}
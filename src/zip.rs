@@ -0,0 +1,148 @@
+//a zip archive writer for the epub export. entries are stored without compression, which every
+//reader accepts and which the epub mimetype entry needs anyway, so no deflate is needed
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+
+//1980-01-01 00:00, the earliest dos date. every entry gets it so the same input gives the same bytes
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+//version 2.0 of the format, the first with folders
+const VERSION: u16 = 20;
+
+//bit 11 says names are utf-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// builds an archive in memory, entries are written in the order they are added
+#[derive(Default)]
+pub struct ZipWriter {
+    output: Vec<u8>,
+    directory: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        ZipWriter::default()
+    }
+
+    /// adds a file, name is its path in the archive with / between folders
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let too_large = |what: &str| format!("Code Generation Error: The archive is too large ({} over the zip limit).", what);
+        let size = u32::try_from(data.len()).map_err(|_| too_large(name))?;
+        let offset = u32::try_from(self.output.len()).map_err(|_| too_large(name))?;
+        let name_length = u16::try_from(name.len()).map_err(|_| too_large(name))?;
+        self.entries = self.entries.checked_add(1).ok_or_else(|| too_large("the number of files"))?;
+        let crc = crc32(data);
+
+        //the local header and the central directory entry share most fields
+        let mut fields = Vec::new();
+        for value in [VERSION, UTF8_NAMES, 0, DOS_TIME, DOS_DATE] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc, size, size] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&name_length.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes()); // no extra field
+
+        self.output.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        self.output.extend_from_slice(&fields);
+        self.output.extend_from_slice(name.as_bytes());
+        self.output.extend_from_slice(data);
+
+        self.directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        self.directory.extend_from_slice(&VERSION.to_le_bytes()); // made by
+        self.directory.extend_from_slice(&fields);
+        //comment length, disk number, internal and external attributes
+        for value in [0u16, 0, 0] {
+            self.directory.extend_from_slice(&value.to_le_bytes());
+        }
+        self.directory.extend_from_slice(&0u32.to_le_bytes());
+        self.directory.extend_from_slice(&offset.to_le_bytes());
+        self.directory.extend_from_slice(name.as_bytes());
+        Ok(())
+    }
+
+    /// the finished archive: the entries, then the central directory and its end record
+    pub fn finish(mut self) -> Result<Vec<u8>, String> {
+        let too_large = || "Code Generation Error: The archive is too large for a zip file.".to_string();
+        let directory_offset = u32::try_from(self.output.len()).map_err(|_| too_large())?;
+        let directory_size = u32::try_from(self.directory.len()).map_err(|_| too_large())?;
+        self.output.append(&mut self.directory);
+        self.output.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+        //this disk, the disk the directory starts on, entries here and in total
+        for value in [0, 0, self.entries, self.entries] {
+            self.output.extend_from_slice(&value.to_le_bytes());
+        }
+        self.output.extend_from_slice(&directory_size.to_le_bytes());
+        self.output.extend_from_slice(&directory_offset.to_le_bytes());
+        self.output.extend_from_slice(&0u16.to_le_bytes()); // no archive comment
+        Ok(self.output)
+    }
+}
+
+/// the crc-32 zip stores for each entry (the ieee polynomial, reflected)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    //the end record leads to the directory, and the directory to each entry and its data
+    #[test]
+    fn test_archive_layout() {
+        let mut zip = ZipWriter::new();
+        zip.add("mimetype", b"application/epub+zip").unwrap();
+        zip.add("OEBPS/caf\u{e9}.txt", b"hello").unwrap();
+        let bytes = zip.finish().unwrap();
+
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), END_OF_DIRECTORY);
+        assert_eq!(u16_at(&bytes, end + 10), 2);
+        let mut at = u32_at(&bytes, end + 16) as usize;
+        let mut names = Vec::new();
+        for _ in 0..2 {
+            assert_eq!(u32_at(&bytes, at), CENTRAL_HEADER);
+            let name_length = u16_at(&bytes, at + 28) as usize;
+            let local = u32_at(&bytes, at + 42) as usize;
+            let name = String::from_utf8(bytes[at + 46..at + 46 + name_length].to_vec()).unwrap();
+
+            assert_eq!(u32_at(&bytes, local), LOCAL_HEADER);
+            let size = u32_at(&bytes, local + 18) as usize;
+            let data = &bytes[local + 30 + name_length..local + 30 + name_length + size];
+            assert_eq!(u32_at(&bytes, local + 14), crc32(data));
+            names.push((name, String::from_utf8(data.to_vec()).unwrap()));
+            at += 46 + name_length;
+        }
+        assert_eq!(names[0], ("mimetype".to_string(), "application/epub+zip".to_string()));
+        assert_eq!(names[1], ("OEBPS/caf\u{e9}.txt".to_string(), "hello".to_string()));
+        //the mimetype has to start right after its header, at byte 38
+        assert_eq!(&bytes[30..38], b"mimetype");
+    }
+}